@@ -16,9 +16,9 @@ use crate::hal::{
     spi::Spi,
 };
 
-use embedded_hal::spi::MODE_0;
+use embedded_hal_02::spi::MODE_0;
 
-use adf4351::{ device::*, register::*, config::*, constants::*, };
+use adf4351::{ device::*, eh02::*, register::*, config::*, constants::*, refin::*, units::*, };
 
 /// Example board config / test frequency generator.
 /// Example boards:
@@ -35,7 +35,7 @@ fn main() -> ! {
     let gpioa = dp.GPIOA.split();
     let mut led1 = gpioa.pa6.into_push_pull_output();
 
-    let mut delay = hal::delay::Delay::new(cp.SYST, clocks);
+    let mut delay = Eh02Delay(hal::delay::Delay::new(cp.SYST, clocks));
 
     let gpiob = dp.GPIOB.split();
     let pin_ce = gpiob.pb10.into_push_pull_output();
@@ -52,57 +52,49 @@ fn main() -> ! {
         clocks,
     );
 
-    let mut sg = Adf4351::new(spi, pin_ce, pin_le);
+    let mut sg = Adf4351::new(Eh02Spi(spi), Eh02Pin(pin_ce), Eh02Pin(pin_le));
     sg.enable().unwrap();
-    let xtal = 25_000_000;
+    // Keep xtal input, clean up duty cycle
+    let refin = RefIn::new_hertz(Hertz::mhz(25), true, 1, true).unwrap();
+    let xtal = refin.f_hz;
+
+    let rs = RegisterSet::recommended_defaults()
+        .with_reference(&refin)
 
-    let rs = RegisterSet::default()
         // Double buffer register writes
         .set(DoubleBuffer::Enabled)
 
-        // FRAC-N modulus
-        .set(Mod(4000))
-
         // Board config
         .set(ChargePumpCurrent(0b111))
         .set(PhaseDetectorPolarity::Positive)
 
-        // Keep xtal input, clean up duty cycle
-        .set(R(1))
-        .set(RefDoubler::Enabled)
-        .set(Rdiv2::Enabled)
-
         // Pin config
         .set(LockDetectPin::DigitalLockDetect)
 
         // RF out
         .set(BandSelectClockDiv(200))
         .set(AuxOutputEnable::Enabled)
-        .set(AuxOutputPower(2))
+        .set(AuxRfPower(RfPower::Plus2dBm))
         .set(RfOutputEnable::Enabled)
-        .set(OutputPower(2))
+        .set(RfPower::Plus2dBm)
         ;
 
-    let f_out = 63_000_000;
-    let rs = FracN::init(rs); // init FracN mode, one time settings
+    let f_out = Hertz::mhz(63);
+    let rs = FracN::init_with_mute_till_lock(rs, true); // init FracN mode, one time settings, no output while settling
     let fracn = FracN(Fpfd::new(xtal, &rs).unwrap()); // init with cuffent PFD config
-    let rs = fracn.set_f_out(f_out, rs).unwrap(); // set output frequency
+    let rs = fracn.set_f_out_with_strategy(f_out, &FixedMod(4000), rs).unwrap(); // set FRAC-N modulus and output frequency
 
-    sg.write_register_set(&mut delay, &rs).unwrap();
+    sg.initialize(&mut delay, &rs).unwrap();
 
     let rs_words = rs.to_words();
     for (i,w) in rs_words.iter().enumerate() {
         hprintln!("RS[{}] {:#010x} {:#034b}", i, w, w).unwrap();
     };
 
-    let int : Int = rs.get();
-    let frac : Frac = rs.get();
-    let modulus : Mod = rs.get();
-    let rfdiv : RfDividerSelect = rs.get();
-    hprintln!("{:?} {:?} {:?} {:?}", int, frac, modulus, rfdiv).unwrap();
+    hprintln!("{}", rs).unwrap();
     hprintln!("{:?} => f set {:?} <-> f actual {:?}", fracn.0, f_out, FracN::f_out_hz(xtal, &rs)).unwrap();
 
-    delay.delay_ms(10000_u32);
+    delay.0.delay_ms(10000_u32);
 
     // Try different frequencies
     let mut step = OUT_FREQ_MAX - OUT_FREQ_MIN;
@@ -113,14 +105,14 @@ fn main() -> ! {
             let rs = fracn.set_f_out(f_out, rs).unwrap(); // set output frequency
             sg.write_register_set(&mut delay, &rs).unwrap();
             hprintln!("[{:012}] {:?} => f set {:012} <-> f actual {:012}", step, fracn.0, f_out, FracN::f_out_hz(xtal, &rs).unwrap()).unwrap();
-            delay.delay_ms(1000_u32);
+            delay.0.delay_ms(1000_u32);
         }
     }
 
     loop {
         led1.set_high().unwrap();
-        delay.delay_ms(1000_u32);
+        delay.0.delay_ms(1000_u32);
         led1.set_low().unwrap();
-        delay.delay_ms(1000_u32);
+        delay.0.delay_ms(1000_u32);
     }
 }
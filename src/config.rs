@@ -1,11 +1,14 @@
 //! Device configuration / frequency calculations
 
-use crate::{ constants::*, errors::*,register::*};
+use core::convert::TryFrom;
+
+use crate::{ constants::*, errors::*, refin::*, register::*, units::Hertz, };
 
 
 /// Phase Frequency Detector' frequency, Hz
 /// f PFD = REF IN × [(1 + D)/(R × (1 + T))]
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Fpfd(pub u32);
 
 impl Fpfd {
@@ -14,22 +17,64 @@ impl Fpfd {
     /// where:
     /// REF IN is the reference frequency input.
     /// D is the RF REF IN doubler bit (0 or 1).
-    /// R is the RF reference division factor (1 to 1023).
+    /// R is the RF reference division factor (1 to 1023), `Error::InvalidRCounter` otherwise.
     /// T is the reference divide-by-2 bit (0 or 1).
+    ///
+    /// REF IN has to be within 10 to 250 MHz (inclusive), see `with_ref_in`
+    /// for lower frequencies, and can't be above 30 MHz with the doubler enabled.
+    /// The PFD frequency maximum depends on the mode: 32 MHz in FRAC-N (`Ldf::FracN` or nonzero FRAC),
+    /// in INT-N 45 MHz with VCO band select (phase adjust off), 90 MHz without.
     pub fn new(
         ref_in_hz: u32,
         rs: &RegisterSet,
     ) -> Result<Self,Error> {
-        (if !(REF_IN_FREQ_MIN .. REF_IN_FREQ_MAX+1).contains(&ref_in_hz) { Err(Error::InvalidReferenceFrequency) } else { Ok(())} )?;
+        Fpfd::checked(ref_in_hz, REF_IN_FREQ_MIN, rs)
+    }
+
+    /// Like `new`, with REF IN in `Hertz`.
+    pub fn new_hertz(
+        ref_in: Hertz,
+        rs: &RegisterSet,
+    ) -> Result<Self,Error> {
+        Fpfd::new(u32::try_from(ref_in).map_err(|_| Error::InvalidReferenceFrequency)?, rs)
+    }
+
+    /// Like `new`, with REF IN frequency and type from the reference path,
+    /// so that a square wave (`RefIn::new_square_wave`) can be below 10 MHz.
+    /// Doubler, R and divide-by-2 still come from the registers, see `RefIn::apply`.
+    pub fn with_ref_in(
+        refin: &RefIn,
+        rs: &RegisterSet,
+    ) -> Result<Self,Error> {
+        Fpfd::checked(refin.f_hz, if refin.square_wave { 1 } else { REF_IN_FREQ_MIN }, rs)
+    }
+
+    fn checked(
+        ref_in_hz: u32,
+        ref_in_min_hz: u32,
+        rs: &RegisterSet,
+    ) -> Result<Self,Error> {
+        (if !(ref_in_min_hz .. REF_IN_FREQ_MAX+1).contains(&ref_in_hz) { Err(Error::InvalidReferenceFrequency) } else { Ok(())} )?;
 
         let doubler : RefDoubler = rs.get();
         let divider : Rdiv2 = rs.get();
         let r : R = rs.get();
-        let fpfd = ref_in_hz * (1 + doubler as u32) / (r.0 as u32) / (1 + divider as u32);
 
-        if fpfd > PFD_FREQ_INTN_MAX {
-            // NOTE this is an absolute max, in FRAC-N mode the limit is even lower, just a sanity check
-            Err(Error::InvalidReferenceFrequency)
+        if matches!(doubler, RefDoubler::Enabled) && ref_in_hz > REF_IN_DOUBLER_FREQ_MAX {
+            return Err(Error::DoublerInputTooHigh);
+        }
+        if !(R_MIN ..= R_MAX).contains(&r.0) {
+            return Err(Error::InvalidRCounter); // R = 0 in a zeroed register set
+        }
+        // at most 250 MHz, the doubler is limited to 30 MHz REF IN
+        let fpfd = (ref_in_hz as u64 * (1 + doubler as u64) / (r.0 as u64 * (1 + divider as u64))) as u32;
+
+        let phase_adj : Ph1PhaseAdj = rs.get();
+
+        if is_frac_n(rs) && fpfd > PFD_FREQ_FRACN_MAX {
+            Err(Error::PfdTooHighForFracN)
+        } else if fpfd > PFD_FREQ_INTN_MAX || (matches!(phase_adj, Ph1PhaseAdj::Off) && fpfd > PFD_FREQ_INTN_BS_MAX) {
+            Err(Error::InvalidPfdFrequency)
         } else {
             Ok(Fpfd(fpfd))
         }
@@ -37,18 +82,560 @@ impl Fpfd {
 }
 
 
+/// FRAC-N mode, `Ldf::FracN` or nonzero FRAC
+fn is_frac_n(rs: &RegisterSet) -> bool {
+    let ldf : Ldf = rs.get();
+    let frac : Frac = rs.get();
+    matches!(ldf, Ldf::FracN) || frac.0 != 0
+}
+
+
+/// Band select clock divider and mode for the PFD frequency.
+/// Picks the smallest divider that keeps the band select clock at or below
+/// 125 kHz in the low mode, falls back to the high mode (500 kHz, divider <= 254)
+/// when the PFD is too fast for that.
+pub fn band_select_clock_div(fpfd_hz: u32) -> (BandSelectClockDiv, BandSelectClockMode) {
+    let div = fpfd_hz.div_ceil(BAND_SELECT_CLOCK_MAX).max(1);
+    if div <= u8::MAX as u32 {
+        (BandSelectClockDiv(div as u8), BandSelectClockMode::Low)
+    } else {
+        let div = fpfd_hz.div_ceil(BAND_SELECT_CLOCK_HIGH_MAX).min(BAND_SELECT_CLOCK_DIV_HIGH_MAX as u32);
+        (BandSelectClockDiv(div as u8), BandSelectClockMode::High)
+    }
+}
+
+
+/// Channel spacing (output frequency resolution, f RES ), Hz.
+/// f RES = f PFD / (MOD × RF Divider), rounded down,
+/// `rf_divider` is the division ratio (1 to 64).
+pub fn channel_spacing(fpfd_hz: u32, modulus: u16, rf_divider: u8) -> u64 {
+    fpfd_hz as u64 / (modulus.max(1) as u64 * rf_divider.max(1) as u64)
+}
+
+
+/// Prescaler for the VCO frequency.
+/// The prescaler divides the VCO output (before the RF divider), so it's the VCO
+/// frequency, not the output frequency, that has to stay within 3.6 GHz for 4/5.
+/// Otherwise 4/5 is picked for its lower INT minimum, 8/9 can't help with a too
+/// low INT (its minimum is 75), that needs a lower PFD frequency.
+pub fn choose_prescaler(vco_hz: u64) -> Pr1Prescaler {
+    if vco_hz > OUT_FREQ_P45_MAX {
+        Pr1Prescaler::Pr89
+    } else {
+        Pr1Prescaler::Pr45
+    }
+}
+
+/// Prescaler for the VCO frequency like `choose_prescaler`, keeping the
+/// `current` one near 3.6 GHz to avoid switching back and forth when sweeping.
+/// 4/5 can't go above 3.6 GHz, so the hysteresis is one-sided:
+/// 8/9 is kept down to `guard_hz` below it (see `PRESCALER_GUARD_BAND_HZ`),
+/// INT still has to be at or above the 8/9 minimum (75) there.
+pub fn choose_prescaler_hysteretic(vco_hz: u64, current: Pr1Prescaler, guard_hz: u64) -> Pr1Prescaler {
+    match current {
+        Pr1Prescaler::Pr89 if vco_hz > OUT_FREQ_P45_MAX.saturating_sub(guard_hz) => Pr1Prescaler::Pr89,
+        _ => choose_prescaler(vco_hz),
+    }
+}
+
+
+/// Swaps the planned prescaler for `choose_prescaler_hysteretic` with the
+/// `current` one, when INT allows it, e.g. to keep 8/9 while sweeping around 3.6 GHz.
+pub(crate) fn keep_prescaler_hysteretic(fpfd: Fpfd, current: Pr1Prescaler, rs: RegisterSet) -> RegisterSet {
+    let (int, frac, modulus, _) = rs.frac_params();
+    let fpfd = fpfd.0 as u64;
+    let nf = int as u64 * fpfd + frac as u64 * fpfd / modulus.max(1) as u64;
+    let prescaler = choose_prescaler_hysteretic(nf, current, PRESCALER_GUARD_BAND_HZ);
+    if check_int(int as u64, prescaler).is_ok() { rs.set(prescaler) } else { rs }
+}
+
+
+/// Checks INT against the prescaler minimum.
+/// Switching prescaler can't help, 8/9 only raises the minimum
+/// and 4/5 is already used whenever the frequency allows it.
+fn check_int(int: u64, prescaler: Pr1Prescaler) -> Result<(), Error> {
+    if int < int_min(prescaler) as u64 { Err(Error::IntTooLow) } else { Ok(()) }
+}
+
+
+/// Checks that FRAC and phase words are less than MOD.
+fn check_frac_phase(rs: &RegisterSet) -> Result<(), Error> {
+    let modulus : Mod = rs.get();
+    let frac : Frac = rs.get();
+    let phase : Phase = rs.get();
+    if frac.0 >= modulus.0 || phase.0 >= modulus.0 { Err(Error::InvalidFrac) } else { Ok(()) }
+}
+
+
+/// Fundamental VCO frequency and RF divider select for the output frequency,
+/// VCO has to stay within 2.2 to 4.4 GHz with at most divide-by-64.
+fn vco_f_and_rf_divider(f_out_hz: u64) -> Result<(u64, u8), Error> {
+    if f_out_hz == 0 {
+        return Err(Error::InvalidOutputFrequency);
+    }
+    let mut vcof = f_out_hz;
+    let mut rf_divider_select = 0;
+    while vcof < VCO_FREQ_MIN && rf_divider_select < RF_DIVIDER_SELECT_MAX { vcof *= 2; rf_divider_select += 1; }
+    if !(VCO_FREQ_MIN ..= VCO_FREQ_MAX).contains(&vcof) {
+        Err(Error::InvalidOutputFrequency)
+    } else {
+        Ok((vcof, rf_divider_select))
+    }
+}
+
+
+/// Frequency at the N counter input: the VCO with fundamental feedback,
+/// the RF divider output with divided feedback (phase-coherent setups).
+fn feedback_hz(vcof: u64, rf_divider_select: u8, feedback: FeedbackSelect) -> u64 {
+    match feedback {
+        FeedbackSelect::Fundamental => vcof,
+        FeedbackSelect::Divided => vcof >> rf_divider_select,
+    }
+}
+
+
+/// Greatest common divisor
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+
+/// Closest FRAC/MOD approximation of num/den (num < den),
+/// with MOD within MOD_MIN ..= MOD_MAX.
+/// Reducing num/den by their GCD gives the exact fraction, which is used
+/// as is when the denominator fits in MOD, otherwise all moduli are tried.
+fn best_frac_mod(num: u64, den: u64) -> (u64, u64) {
+    let g = gcd(num, den);
+    let (frac, modulus) = (num / g, den / g);
+
+    if modulus <= MOD_MAX as u64 {
+        // 0/1 is the only fraction with a too small denominator
+        return if modulus < MOD_MIN as u64 { (0, MOD_MIN as u64) } else { (frac, modulus) };
+    }
+
+    // error of f/m is |f/m - num/den| = |f*den - num*m| / (m*den)
+    let mut best = (0, MOD_MIN as u64);
+    let mut best_err = num * MOD_MIN as u64;
+    for m in MOD_MIN as u64 ..= MOD_MAX as u64 {
+        let f = (num * m + den / 2) / den;
+        let err = (f * den).abs_diff(num * m);
+        if err * best.1 < best_err * m {
+            best = (f, m);
+            best_err = err;
+        }
+    }
+    best
+}
+
+
+/// Synthesis mode
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SynthMode {
+    /// INT-N, FRAC = 0
+    IntegerN,
+    /// FRAC-N
+    FractionalN,
+}
+
+
+/// Lock detect function (`Ldf`, PFD cycles counted) and precision
+/// (`Ldp`, phase window), which the datasheet pairs per mode:
+/// 40 cycles and 10 ns for FRAC-N, 5 cycles and 6 ns for INT-N.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LockDetectConfig {
+    /// Lock detect function
+    pub ldf: Ldf,
+    /// Lock detect precision
+    pub ldp: Ldp,
+}
+
+impl LockDetectConfig {
+    /// FRAC-N pairing, 40 PFD cycles, 10 ns
+    pub const fn frac_n() -> Self {
+        LockDetectConfig { ldf: Ldf::FracN, ldp: Ldp::Ldp10ns, }
+    }
+
+    /// INT-N pairing, 5 PFD cycles, 6 ns
+    pub const fn int_n() -> Self {
+        LockDetectConfig { ldf: Ldf::IntN, ldp: Ldp::Ldp6ns, }
+    }
+
+    /// Lock detect settings currently in R2
+    pub fn from_registers(rs: &RegisterSet) -> Self {
+        LockDetectConfig { ldf: rs.get(), ldp: rs.get(), }
+    }
+
+    /// Recommended pairing, one of `frac_n` or `int_n`
+    pub fn is_recommended(&self) -> bool {
+        matches!((self.ldf, self.ldp), (Ldf::FracN, Ldp::Ldp10ns) | (Ldf::IntN, Ldp::Ldp6ns))
+    }
+
+    /// Writes lock detect function and precision to R2.
+    pub fn apply(&self, rs: RegisterSet) -> RegisterSet {
+        rs.set(self.ldf).set(self.ldp)
+    }
+}
+
+
+/// FRAC-N register combinations per use case.
+///
+/// All of them use the FRAC-N lock detect (`Ldf::FracN`, `Ldp::Ldp10ns`),
+/// 6 ns antibacklash pulse and no charge cancellation, the datasheet
+/// recommends the 3 ns pulse and charge cancellation for INT-N only.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Optimization {
+    /// Dither off, best phase noise, suits narrow loop filters
+    LowNoise,
+    /// Dither on, spurs spread into noise, suits wide loop filters
+    LowSpur,
+    /// Low spur mode with the fast band select logic (`BandSelectClockMode::High`),
+    /// band select clock divider has to suit the high mode, see `band_select_clock_div`
+    FastLock,
+}
+
+impl Optimization {
+    /// Writes noise mode, lock detect, antibacklash, charge cancellation
+    /// and band select clock mode settings.
+    /// `FastLock` checks the band select clock divider against the high mode limit,
+    /// see `BandSelectClockDiv::new`.
+    pub fn apply(self, rs: RegisterSet) -> Result<RegisterSet, Error> {
+        let rs = rs.set_synth_mode(SynthMode::FractionalN);
+        match self {
+            Optimization::LowNoise => Ok(rs.set(NoiseMode::LowNoise).set(BandSelectClockMode::Low)),
+            Optimization::LowSpur => Ok(rs.set(NoiseMode::LowSpur).set(BandSelectClockMode::Low)),
+            Optimization::FastLock => {
+                let bscd : BandSelectClockDiv = rs.get();
+                BandSelectClockDiv::new(bscd.0, BandSelectClockMode::High)?;
+                Ok(rs.set(NoiseMode::LowSpur).set(BandSelectClockMode::High))
+            },
+        }
+    }
+}
+
+
+/// Modulus (MOD) policy for `FracN::set_f_out_with_strategy`,
+/// returned MOD must be within MOD_MIN ..= MOD_MAX.
+pub trait ModulusStrategy {
+    /// MOD to use at the given PFD frequency for the N counter input frequency,
+    /// the VCO frequency with fundamental feedback, the output frequency with
+    /// divided feedback (`FeedbackSelect::Divided`).
+    fn modulus(&self, fpfd: u32, feedback_hz: u64) -> u16;
+}
+
+/// Fixed MOD, clamped to MOD_MIN ..= MOD_MAX
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FixedMod(pub u16);
+
+impl ModulusStrategy for FixedMod {
+    fn modulus(&self, _fpfd: u32, _feedback_hz: u64) -> u16 {
+        self.0.clamp(MOD_MIN, MOD_MAX)
+    }
+}
+
+/// Maximum MOD, finest resolution
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MaxMod;
+
+impl ModulusStrategy for MaxMod {
+    fn modulus(&self, _fpfd: u32, _feedback_hz: u64) -> u16 {
+        MOD_MAX
+    }
+}
+
+/// MOD = f PFD / GCD(f PFD, channel offset) when it fits in 12 bits,
+/// closest FRAC/MOD approximation otherwise, see `FracN::set_f_out_exact`.
+/// Falls back to `MaxMod` for a zero PFD frequency.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OptimalMod;
+
+impl ModulusStrategy for OptimalMod {
+    fn modulus(&self, fpfd: u32, feedback_hz: u64) -> u16 {
+        if fpfd == 0 {
+            return MOD_MAX;
+        }
+        u16::try_from(best_frac_mod(feedback_hz % fpfd as u64, fpfd as u64).1).unwrap_or(MOD_MAX)
+    }
+}
+
+
+/// Frequency planning options
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PlanOptions {
+    /// Fixed MOD (2 to 4095), FRAC is rounded down.
+    /// `None` picks the MOD that gets closest to the target, see `FracN::set_f_out_exact`.
+    pub modulus: Option<u16>,
+    /// Noise/spur settings to apply along with a FRAC-N plan, `None` keeps registers as they are
+    pub optimize: Option<Optimization>,
+    /// Picks the mode: INT-N (MOD = 1) when FRAC comes out 0, FRAC-N otherwise,
+    /// and sets the mode's registers (see `RegisterSet::set_synth_mode`).
+    /// When off, the plan is FRAC-N and mode registers are left as they are.
+    pub integer_n: bool,
+    /// VCO feedback path to the N counter, `FeedbackSelect::Divided` takes it
+    /// after the RF divider, which keeps multiple PLLs phase-coherent.
+    pub feedback: FeedbackSelect,
+}
+
+/// Closest MOD, INT-N when possible, no noise/spur settings, fundamental feedback
+impl Default for PlanOptions {
+    fn default() -> Self {
+        PlanOptions { modulus: None, optimize: None, integer_n: true, feedback: FeedbackSelect::Fundamental, }
+    }
+}
+
+
+/// FRAC-N solution for a target output frequency,
+/// computed without touching registers, see `apply`.
+/// RF OUT = [INT + (FRAC/MOD)] × (f PFD /RF Divider), fundamental feedback;
+/// RF OUT = [INT + (FRAC/MOD)] × f PFD, divided feedback.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrequencyPlan {
+    /// PFD frequency
+    pub fpfd: Fpfd,
+    /// INT
+    pub int: u16,
+    /// FRAC
+    pub frac: u16,
+    /// MOD
+    pub modulus: u16,
+    /// RF divider select, divide by 2^`rf_divider_select`
+    pub rf_divider_select: u8,
+    /// Prescaler
+    pub prescaler: Pr1Prescaler,
+    /// Actual output frequency, Hz
+    pub achieved_hz: u64,
+    /// Noise/spur settings
+    pub optimize: Option<Optimization>,
+    /// Mode to set, `None` leaves mode registers as they are
+    pub synth_mode: Option<SynthMode>,
+    /// VCO feedback path
+    pub feedback: FeedbackSelect,
+}
+
+impl FrequencyPlan {
+
+    /// Solves for the target output frequency with the given reference path,
+    /// PFD frequency has to be within the FRAC-N limit.
+    pub fn solve(
+        refin: &RefIn,
+        target_hz: u64,
+        options: PlanOptions,
+    ) -> Result<Self, Error> {
+        refin.validate()?;
+        let fpfd_hz = refin.f_pfd_hz();
+        let plan = FrequencyPlan::with_fpfd(Fpfd(fpfd_hz), target_hz, options)?;
+        if plan.is_integer_n() {
+            if fpfd_hz > PFD_FREQ_INTN_BS_MAX {
+                return Err(Error::InvalidPfdFrequency);
+            }
+        } else if fpfd_hz > PFD_FREQ_FRACN_MAX {
+            return Err(Error::PfdTooHighForFracN);
+        }
+        Ok(plan)
+    }
+
+    /// Closest achievable output frequency to the target, even outside of the
+    /// output range, which is clamped to `OUT_FREQ_MIN ..= OUT_FREQ_MAX`.
+    /// MOD is picked per frequency (see `PlanOptions::default`), INT-N when exact.
+    /// Returns the plan and its error (achieved - target), Hz.
+    pub fn nearest(refin: &RefIn, target_hz: u64) -> Result<(Self, i64), Error> {
+        let plan = FrequencyPlan::solve(refin, target_hz.clamp(OUT_FREQ_MIN, OUT_FREQ_MAX), PlanOptions::default())?;
+        Ok((plan, plan.achieved_hz as i64 - target_hz as i64))
+    }
+
+    /// Offsets of the `N` spurs nearest to the carrier, Hz, rounded down, ascending.
+    ///
+    /// Spur model: the Σ-Δ modulator repeats with the reduced fraction FRAC/MOD
+    /// (divided by GCD(FRAC, MOD)), so fractional spurs fall at multiples of
+    /// f PFD / reduced MOD from the carrier at the N counter input, which is
+    /// f PFD itself (reference spurs) in INT-N. Integer boundary spurs are
+    /// among them, see `integer_boundary_spur_hz`. Offsets scale down by
+    /// the RF divider at the output with fundamental feedback.
+    pub fn spur_offsets<const N: usize>(&self) -> [u64; N] {
+        let (mod_r, div) = self.spur_mod_and_divider();
+        let mut offsets = [0; N];
+        for (k, o) in offsets.iter_mut().enumerate() {
+            *o = (k as u64 + 1) * self.fpfd.0 as u64 / (mod_r * div);
+        }
+        offsets
+    }
+
+    /// Integer boundary spur offset, Hz, rounded down: distance from the carrier to
+    /// the closest integer multiple of f PFD at the N counter input, scaled like
+    /// `spur_offsets`. Strongest when within the loop bandwidth, `None` in INT-N.
+    pub fn integer_boundary_spur_hz(&self) -> Option<u64> {
+        let (mod_r, div) = self.spur_mod_and_divider();
+        let frac_r = self.frac as u64 * mod_r / self.modulus.max(1) as u64;
+        if frac_r == 0 {
+            return None;
+        }
+        Some(frac_r.min(mod_r - frac_r) * self.fpfd.0 as u64 / (mod_r * div))
+    }
+
+    /// Reduced MOD, and the divider that spur offsets scale down by at the output
+    fn spur_mod_and_divider(&self) -> (u64, u64) {
+        let modulus = self.modulus.max(1) as u64;
+        let mod_r = modulus / gcd(self.frac as u64, modulus);
+        let div = match self.feedback {
+            FeedbackSelect::Fundamental => 1 << self.rf_divider_select,
+            FeedbackSelect::Divided => 1,
+        };
+        (mod_r, div)
+    }
+
+    /// INT-N solution, FRAC = 0 with `PlanOptions::integer_n`
+    pub fn is_integer_n(&self) -> bool {
+        self.synth_mode == Some(SynthMode::IntegerN)
+    }
+
+    /// Solves for the target output frequency with a known PFD frequency.
+    pub fn with_fpfd(
+        fpfd: Fpfd,
+        target_hz: u64,
+        options: PlanOptions,
+    ) -> Result<Self, Error> {
+        (if !(OUT_FREQ_MIN .. OUT_FREQ_MAX+1).contains(&target_hz) { Err(Error::InvalidOutputFrequency) } else { Ok(())} ) ?;
+        if fpfd.0 == 0 {
+            return Err(Error::InvalidPfdFrequency);
+        }
+
+        let (vcof, rf_divider_select) = vco_f_and_rf_divider(target_hz)?;
+        // the prescaler is part of the N counter, it sees the feedback frequency
+        let nf = feedback_hz(vcof, rf_divider_select, options.feedback);
+        let prescaler = choose_prescaler(nf);
+        let fpfd_hz = fpfd.0 as u64;
+
+        // N counter input / f_PFD = INT + FRAC/MOD
+        let (int, frac, modulus) = match options.modulus {
+            Some(modulus) => {
+                let modulus = Mod::new(modulus)?.0 as u64;
+                let nscaled = (nf * modulus) / fpfd_hz;
+                (nscaled / modulus, nscaled % modulus, modulus)
+            },
+            None => {
+                let (frac, modulus) = best_frac_mod(nf % fpfd_hz, fpfd_hz);
+                if frac == modulus { // rounded up to the next integer
+                    (nf / fpfd_hz + 1, 0, modulus)
+                } else {
+                    (nf / fpfd_hz, frac, modulus)
+                }
+            },
+        };
+        check_int(int, prescaler)?;
+        let int16 = u16::try_from(int).map_err(|_| Error::IntTooHigh)?;
+
+        let synth_mode = match (options.integer_n, frac) {
+            (false, _) => None,
+            (true, 0) => Some(SynthMode::IntegerN),
+            (true, _) => Some(SynthMode::FractionalN),
+        };
+        let modulus = if synth_mode == Some(SynthMode::IntegerN) { 1 } else { modulus };
+
+        Ok(FrequencyPlan {
+            fpfd,
+            int: int16,
+            frac: u16::try_from(frac).map_err(|_| Error::ArithmeticOverflow)?,
+            modulus: u16::try_from(modulus).map_err(|_| Error::ArithmeticOverflow)?,
+            rf_divider_select,
+            prescaler,
+            achieved_hz: match options.feedback {
+                FeedbackSelect::Fundamental => (int * fpfd_hz + frac * fpfd_hz / modulus) >> rf_divider_select,
+                FeedbackSelect::Divided => int * fpfd_hz + frac * fpfd_hz / modulus,
+            },
+            optimize: options.optimize,
+            synth_mode,
+            feedback: options.feedback,
+        })
+    }
+
+    /// Writes INT, FRAC, MOD, RF divider, prescaler, feedback, mode and `Optimization` settings,
+    /// reference path (R2) is left as is, see `RefIn::apply`.
+    /// INT-N plans turn dither off (`NoiseMode::LowNoise`), `Optimization` only applies to FRAC-N.
+    pub fn apply(&self, rs: RegisterSet) -> Result<RegisterSet, Error> {
+        let rs = rs.set(Int(self.int))
+                   .set(Frac(self.frac))
+                   .set(Mod(self.modulus))
+                   .set(RfDividerSelect(self.rf_divider_select))
+                   .set(self.prescaler)
+                   .set(self.feedback);
+        let rs = match self.synth_mode {
+            Some(SynthMode::IntegerN) => return Ok(rs.set_synth_mode(SynthMode::IntegerN).set(NoiseMode::LowNoise)),
+            Some(SynthMode::FractionalN) => rs.set_synth_mode(SynthMode::FractionalN),
+            None => rs,
+        };
+        match self.optimize {
+            Some(optimize) => optimize.apply(rs),
+            None => Ok(rs),
+        }
+    }
+}
+
+
+/// Registers for the output frequency from a REF IN frequency, in one call.
+///
+/// Starts with `RegisterSet::recommended_defaults`, divides REF IN down to
+/// the FRAC-N PFD limit (32 MHz) with the R counter, no doubler or divide-by-2,
+/// sets the band select clock for the PFD frequency and solves for the closest
+/// output frequency (INT-N when it's exact, see `FrequencyPlan::solve`).
+/// See `FrequencyPlan`, `RefIn` and `FracN` for finer control.
+pub fn registers_for(ref_in_hz: u32, out_hz: u64) -> Result<RegisterSet, Error> {
+    let r = ref_in_hz.div_ceil(PFD_FREQ_FRACN_MAX).max(R_MIN as u32);
+    let refin = RefIn::new(ref_in_hz, false, r.min(R_MAX as u32) as u16, false)?;
+    registers_for_refin(&refin, out_hz)
+}
+
+/// Like `registers_for`, with the reference path steered toward the target
+/// PFD frequency instead of the highest one, e.g. a lower PFD for better
+/// in-band noise, see `RegisterSet::set_reference`.
+pub fn registers_for_pfd(ref_in_hz: u32, target_pfd_hz: u32, out_hz: u64) -> Result<RegisterSet, Error> {
+    let rs = RegisterSet::default().set_reference(ref_in_hz, target_pfd_hz)?;
+    registers_for_refin(&RefIn::from_registers(ref_in_hz, &rs), out_hz)
+}
+
+fn registers_for_refin(refin: &RefIn, out_hz: u64) -> Result<RegisterSet, Error> {
+    let plan = FrequencyPlan::solve(refin, out_hz, PlanOptions::default())?;
+
+    let (bscd, bscm) = band_select_clock_div(refin.f_pfd_hz());
+    let rs = FracN::init(RegisterSet::recommended_defaults().with_reference(refin))
+        .set(bscd)
+        .set(bscm);
+    let rs = plan.apply(rs)?;
+    rs.validate_ref_in(refin)?;
+    Ok(rs)
+}
+
+
 /// FRAC-N frequency settings
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct FracN(pub Fpfd);
 
 impl FracN {
 
     /// Initialize FracN mode
     pub fn init(rs: RegisterSet) -> RegisterSet {
-        rs.set(FeedbackSelect::Fundamental) // set_f_out calculation is based on Fundamental VCO feedback frequency
-          .set(Ldf::FracN)
-          .set(Ldp::Ldp10ns)
-          .set(AntiBacklashPulseWidth::AB6ns)
+        rs.set(FeedbackSelect::Fundamental) // set FeedbackSelect::Divided after init for phase-coherent setups
+          .set_synth_mode(SynthMode::FractionalN)
+    }
+
+    /// Initialize FracN mode with register combinations for the use case
+    pub fn init_optimized(rs: RegisterSet, optimize: Optimization) -> Result<RegisterSet, Error> {
+        optimize.apply(FracN::init(rs))
+    }
+
+    /// Initialize FracN mode, optionally muting RF outputs until the PLL locks
+    /// (`MuteTillLockDetect`), so that retuning doesn't radiate while settling.
+    /// Muting relies on digital lock detect, `init` sets it up for FRAC-N.
+    pub fn init_with_mute_till_lock(rs: RegisterSet, mute: bool) -> RegisterSet {
+        FracN::init(rs)
+            .set(if mute { MuteTillLockDetect::Enabled } else { MuteTillLockDetect::Disabled })
     }
 
 
@@ -56,37 +643,102 @@ impl FracN {
     /// Actual frequency will depend on the REF IN and modulus settings.
     pub fn set_f_out(
         self: &Self,
-        f_out_hz: u64,
+        f_out: impl Into<Hertz>,
         rs: RegisterSet
     ) -> Result<RegisterSet, Error> {
-        (if !(OUT_FREQ_MIN .. OUT_FREQ_MAX+1).contains(&f_out_hz) { Err(Error::InvalidOutputFrequency) } else { Ok(())} ) ?;
+        let f_out_hz = f_out.into().0;
+        let rmod : Mod = rs.get();
+        let plan = FrequencyPlan::with_fpfd(self.0, f_out_hz, PlanOptions { modulus: Some(rmod.0), optimize: None, integer_n: false, feedback: rs.get(), })?;
 
-        let prescaler : Pr1Prescaler  =
-            if f_out_hz > OUT_FREQ_P45_MAX {
-                Pr1Prescaler::Pr89
-            } else {
-                Pr1Prescaler::Pr45
-            };
+        let rs = plan.apply(rs)?;
+        check_frac_phase(&rs)?;
+        Ok(rs)
+    }
 
-        let mut vcof = f_out_hz;
-        let mut rf_divider_select = 0;
-        while vcof < VCO_FREQ_MIN { vcof *= 2; rf_divider_select += 1; }
 
-        let rmod : Mod = rs.get();
-        let modulus = rmod.0 as u64;
+    /// Sets output frequency like `set_f_out`, with MOD picked by the strategy
+    /// instead of the one already in the registers, for the registers' feedback selection.
+    pub fn set_f_out_with_strategy<S: ModulusStrategy>(
+        &self,
+        f_out: impl Into<Hertz>,
+        strategy: &S,
+        rs: RegisterSet
+    ) -> Result<RegisterSet, Error> {
+        let f_out_hz = f_out.into().0;
+        let (vcof, rf_divider_select) = vco_f_and_rf_divider(f_out_hz)?;
+        let modulus = strategy.modulus(self.0.0, feedback_hz(vcof, rf_divider_select, rs.get()));
+        self.set_f_out(f_out_hz, rs.set(Mod::new(modulus)?))
+    }
 
-        // RF OUT = [INT + (FRAC/MOD)] × (f PFD /RF Divider)
-        // RF_OUT * RF Divider / f_PFD = INT + FRAC/MOD
-        let nscaled = (vcof * modulus) / self.0.0 as u64;
-        let int = nscaled / modulus;
-        let frac = nscaled % modulus;
 
-        Ok (
-            rs.set(Int(int as u16))
-              .set(Frac(frac as u16))
-              .set(RfDividerSelect(rf_divider_select))
-              .set(prescaler)
-        )
+    /// Retunes from `old_hz` to `new_hz` like `set_f_out`, skipping VCO band selection
+    /// (`Ph1PhaseAdj::On`) when the change is below `threshold_hz` and the RF divider
+    /// stays the same. The datasheet recommends skipping it only for deviations below
+    /// 1 MHz (`BAND_SELECT_SKIP_MAX_HZ`). Phase adjust also turns off phase resync.
+    /// Returns updated registers and whether band selection (full re-lock) will run.
+    pub fn retune(
+        &self,
+        old_hz: u64,
+        new_hz: u64,
+        threshold_hz: u64,
+        rs: RegisterSet
+    ) -> Result<(RegisterSet, bool), Error> {
+        let rs_new = self.set_f_out(new_hz, rs)?;
+        let rfdiv_old : RfDividerSelect = rs.get();
+        let rfdiv_new : RfDividerSelect = rs_new.get();
+        let band_select = old_hz.abs_diff(new_hz) >= threshold_hz || rfdiv_old.0 != rfdiv_new.0;
+        let phase_adj = if band_select { Ph1PhaseAdj::Off } else { Ph1PhaseAdj::On };
+        Ok((rs_new.set(phase_adj), band_select))
+    }
+
+
+    /// Sets output frequency like `set_f_out`, also returns
+    /// the frequency error (actual - requested), Hz.
+    pub fn set_f_out_checked(
+        &self,
+        f_out_hz: u64,
+        ref_in_hz: u32,
+        rs: RegisterSet
+    ) -> Result<(RegisterSet, i64), Error> {
+        let rs = self.set_f_out(f_out_hz, rs)?;
+        let f_actual_hz = FracN::f_out_hz(ref_in_hz, &rs)?;
+        Ok((rs, f_actual_hz as i64 - f_out_hz as i64))
+    }
+
+
+    /// Sets output frequency like `set_f_out`, but with the PFD frequency
+    /// recalculated from the measured reference, e.g. after a GPSDO correction,
+    /// instead of the nominal one. Reference resolution is 1 Hz.
+    /// Returns updated registers and actual output frequency under that reference.
+    pub fn set_f_out_with_ref(
+        &self,
+        f_out_hz: u64,
+        actual_ref_hz: u32,
+        rs: RegisterSet
+    ) -> Result<(RegisterSet, u64), Error> {
+        let fracn = FracN(Fpfd::new(actual_ref_hz, &rs)?);
+        let rs = fracn.set_f_out(f_out_hz, rs)?;
+        Ok((rs, FracN::f_out_hz(actual_ref_hz, &rs)?))
+    }
+
+
+    /// Sets output frequency choosing the modulus (MOD) that gets closest to the desired.
+    /// Returns updated registers and actual output frequency.
+    ///
+    /// MOD = f PFD / GCD(f PFD, channel offset) gives an exact frequency when it fits
+    /// in 12 bits, otherwise the closest FRAC/MOD approximation is used.
+    pub fn set_f_out_exact(
+        &self,
+        f_out_hz: u64,
+        ref_in_hz: u32,
+        rs: RegisterSet
+    ) -> Result<(RegisterSet, u64), Error> {
+        let plan = FrequencyPlan::with_fpfd(self.0, f_out_hz, PlanOptions { modulus: None, optimize: None, integer_n: false, feedback: rs.get(), })?;
+
+        let rs = plan.apply(rs)?;
+        check_frac_phase(&rs)?;
+
+        Ok((rs, FracN::f_out_hz(ref_in_hz, &rs)?))
     }
 
 
@@ -99,25 +751,714 @@ impl FracN {
     /// FRAC is the numerator of the fractional division (0 to MOD − 1).
     /// MOD is the preset fractional modulus (2 to 4095).
     /// RF Divider is the output divider that divides down the
-    /// VCO frequency.
+    /// VCO frequency, it's inside the loop with divided feedback
+    /// (`FeedbackSelect::Divided`): RF OUT = [INT + (FRAC/MOD)] × f PFD.
+    ///
+    /// FRAC has to be less than MOD, MOD = 0 is only accepted with FRAC = 0 (INT-N).
     pub fn f_out_hz(ref_in_hz: u32, rs: &RegisterSet) -> Result<u64,Error> {
-        let int : Int = rs.get();
-        let int = int.0 as u64;
-
-        let frac : Frac = rs.get();
-        let frac = frac.0 as u64;
-
-        let modulus : Mod = rs.get();
-        let modulus = modulus.0 as u64;
+        FracN::f_out_hz_with(|rs| Fpfd::new(ref_in_hz, rs), rs)
+    }
 
-        let rfdiv : RfDividerSelect = rs.get();
-        let rfdiv : u64 = 1 << rfdiv.0;
+    fn f_out_hz_with<F>(fpfd: F, rs: &RegisterSet) -> Result<u64,Error>
+    where F: FnOnce(&RegisterSet) -> Result<Fpfd,Error>,
+    {
+        let (int, frac, modulus, rfdiv) = rs.frac_params();
+        let (int, frac, modulus, rfdiv) = (int as u64, frac as u64, modulus as u64, rfdiv as u64);
+        if frac != 0 && frac >= modulus {
+            return Err(Error::InvalidFrac);
+        }
+        let modulus = modulus.max(1);
 
-        let fpfd = Fpfd::new(ref_in_hz, rs)?;
+        let fpfd = fpfd(rs)?;
         let fpfd = fpfd.0 as u64;
 
+        let feedback : FeedbackSelect = rs.get();
+        let rfdiv = if matches!(feedback, FeedbackSelect::Divided) { 1 } else { rfdiv };
+
         Ok(
             (int*fpfd + frac*fpfd/modulus) / rfdiv
         )
     }
 }
+
+
+/// INT-N frequency settings
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IntN(pub Fpfd);
+
+impl IntN {
+
+    /// Initialize IntN mode
+    pub fn init(rs: RegisterSet) -> RegisterSet {
+        rs.set(FeedbackSelect::Fundamental) // set FeedbackSelect::Divided after init for phase-coherent setups
+          .set(Frac(0))
+          .set(Mod(1))
+          .set_synth_mode(SynthMode::IntegerN)
+    }
+
+    /// Initialize IntN mode, optionally muting RF outputs until the PLL locks,
+    /// see `FracN::init_with_mute_till_lock`.
+    pub fn init_with_mute_till_lock(rs: RegisterSet, mute: bool) -> RegisterSet {
+        IntN::init(rs)
+            .set(if mute { MuteTillLockDetect::Enabled } else { MuteTillLockDetect::Disabled })
+    }
+
+
+    /// Sets output frequency, which has to be a multiple of the
+    /// PFD frequency at the N counter input (see `FracN::f_out_hz`).
+    /// RF OUT = INT × (f PFD /RF Divider), or INT × f PFD with divided feedback
+    pub fn set_f_out(
+        &self,
+        f_out: impl Into<Hertz>,
+        rs: RegisterSet
+    ) -> Result<RegisterSet, Error> {
+        let f_out_hz = f_out.into().0;
+        (if !(OUT_FREQ_MIN .. OUT_FREQ_MAX+1).contains(&f_out_hz) { Err(Error::InvalidOutputFrequency) } else { Ok(())} ) ?;
+
+        let (vcof, rf_divider_select) = vco_f_and_rf_divider(f_out_hz)?;
+        let nf = feedback_hz(vcof, rf_divider_select, rs.get());
+        let prescaler = choose_prescaler(nf);
+
+        let fpfd = self.0.0 as u64;
+        if !nf.is_multiple_of(fpfd) {
+            return Err(Error::InexactOutputFrequency);
+        }
+        let int = nf / fpfd;
+        check_int(int, prescaler)?;
+        let int = u16::try_from(int).map_err(|_| Error::IntTooHigh)?;
+
+        Ok (
+            rs.set(Int(int))
+              .set(RfDividerSelect(rf_divider_select))
+              .set(prescaler)
+        )
+    }
+}
+
+
+/// Clock divider value for a timeout, rounded up so that the timeout is at least `t_us`.
+/// t = CLK_DIV_VALUE × MOD / f PFD
+fn clock_divider_value(fpfd: Fpfd, rs: &RegisterSet, t_us: u32) -> Result<ClockDividerValue, Error> {
+    let modulus : Mod = rs.get();
+    let ticks_per_div = modulus.0.max(1) as u64 * 1_000_000;
+    let div = (t_us as u64 * fpfd.0 as u64).div_ceil(ticks_per_div).max(1);
+    if div > CLOCK_DIVIDER_MAX as u64 {
+        Err(Error::InvalidClockDivider)
+    } else {
+        Ok(ClockDividerValue(div as u16))
+    }
+}
+
+
+/// Phase resync settings.
+/// Phase resync makes the output phase deterministic after R0 updates,
+/// it's applied after the timeout set by the clock divider, which has to
+/// cover the PLL settling time.
+/// Phase resync relies on VCO band selection, which phase adjust
+/// (`Ph1PhaseAdj::On`) disables, so the two can't be used together.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PhaseResync(pub Fpfd);
+
+impl PhaseResync {
+
+    /// Enables phase resync with the timeout of at least `timeout_us`,
+    /// depends on the current MOD, so has to be set after it.
+    /// Turns off phase adjust.
+    pub fn set_timeout(
+        &self,
+        timeout_us: u32,
+        rs: RegisterSet
+    ) -> Result<RegisterSet, Error> {
+        let cdv = clock_divider_value(self.0, &rs, timeout_us)?;
+        Ok (
+            rs.set(ClockDividerMode::Resync)
+              .set(cdv)
+              .set(Ph1PhaseAdj::Off)
+        )
+    }
+}
+
+
+/// Fast lock settings.
+/// During the fast lock window the charge pump runs at the maximum current
+/// and the SW pin shorts part of the loop filter, widening the loop bandwidth,
+/// then the charge pump drops back to the programmed current.
+/// For the datasheet's 16× current boost the programmed current is the lowest (312.5 µA).
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FastLock(pub Fpfd);
+
+impl FastLock {
+
+    /// Enables fast lock for at least `timeout_us`, typically a few
+    /// time constants of the fast lock loop filter.
+    /// Depends on the current MOD, so has to be set after it.
+    pub fn set_timeout(
+        &self,
+        timeout_us: u32,
+        rs: RegisterSet
+    ) -> Result<RegisterSet, Error> {
+        let cdv = clock_divider_value(self.0, &rs, timeout_us)?;
+        Ok (
+            rs.set(ClockDividerMode::FastLock)
+              .set(cdv)
+        )
+    }
+
+    /// Sets the charge pump current used once the fast lock window is over,
+    /// closest setting to `ua`, µA, the loop filter is designed for.
+    pub fn set_charge_pump_current_ua(
+        &self,
+        ua: u32,
+        rs: RegisterSet
+    ) -> RegisterSet {
+        rs.set(ChargePumpCurrent::closest_to_ua(ua))
+    }
+}
+
+
+/// Frequency queries on the register set
+impl RegisterSet {
+
+    /// Sets lock detect function and precision, antibacklash pulse width
+    /// and charge cancellation to the datasheet recommendations for the mode:
+    /// FRAC-N: 40 cycles, 10 ns, 6 ns, off; INT-N: 5 cycles, 6 ns, 3 ns, on.
+    pub fn set_synth_mode(self, mode: SynthMode) -> RegisterSet {
+        match mode {
+            SynthMode::FractionalN =>
+                LockDetectConfig::frac_n().apply(self)
+                    .set(AntiBacklashPulseWidth::AB6ns)
+                    .set(ChargeCancellation::Disabled),
+            SynthMode::IntegerN =>
+                LockDetectConfig::int_n().apply(self)
+                    .set(AntiBacklashPulseWidth::AB3ns)
+                    .set(ChargeCancellation::Enabled),
+        }
+    }
+
+    /// Phase Frequency Detector' frequency, Hz, see `Fpfd::new`.
+    pub fn f_pfd_hz(&self, ref_in_hz: u32) -> Result<u32, Error> {
+        Fpfd::new(ref_in_hz, self).map(|f| f.0)
+    }
+
+    /// Total feedback division N = INT + FRAC/MOD, × 1000, rounded down.
+    /// With `f_pfd_hz`, gives loop filter design inputs without floats.
+    pub fn n_total_milli(&self) -> u64 {
+        let int : Int = self.get();
+        let frac : Frac = self.get();
+        let modulus : Mod = self.get();
+        let modulus = modulus.0.max(1) as u64;
+        (int.0 as u64 * modulus + frac.0 as u64) * 1000 / modulus
+    }
+
+    /// Smallest output frequency step for the current MOD and RF divider, Hz,
+    /// see `channel_spacing`.
+    pub fn channel_spacing_hz(&self, ref_in_hz: u32) -> Result<u64, Error> {
+        let modulus : Mod = self.get();
+        Ok(channel_spacing(self.f_pfd_hz(ref_in_hz)?, modulus.0, self.rf_divider()))
+    }
+
+    /// VCO band selection time, µs, rounded up: `BAND_SELECT_CYCLES` periods
+    /// of the band select clock (f PFD / band select clock divider).
+    /// Lock takes at least that long after an R0 write that runs band selection.
+    pub fn band_select_time_us(&self, ref_in_hz: u32) -> Result<u32, Error> {
+        let bscd : BandSelectClockDiv = self.get();
+        if bscd.0 == 0 {
+            return Err(Error::InvalidBandSelectClockDiv);
+        }
+        let fpfd = self.f_pfd_hz(ref_in_hz)? as u64;
+        Ok((BAND_SELECT_CYCLES * bscd.0 as u64 * 1_000_000).div_ceil(fpfd) as u32)
+    }
+
+    /// RF divider division ratio (1 to 64)
+    pub fn rf_divider(&self) -> u8 {
+        let rfdiv : RfDividerSelect = self.get();
+        1 << rfdiv.0
+    }
+
+    /// INT, FRAC, MOD and RF divider division ratio (1 to 64) at once,
+    /// RF OUT = [INT + (FRAC/MOD)] × (f PFD /RF Divider)
+    pub fn frac_params(&self) -> (u16, u16, u16, u8) {
+        let int : Int = self.get();
+        let frac : Frac = self.get();
+        let modulus : Mod = self.get();
+        (int.0, frac.0, modulus.0, self.rf_divider())
+    }
+
+    /// VCO frequency, Hz, output frequency before the RF divider,
+    /// has to be within 2.2 to 4.4 GHz for a valid configuration.
+    pub fn vco_freq_hz(&self, ref_in_hz: u32) -> Result<u64, Error> {
+        Ok(self.f_out_hz(ref_in_hz)? * self.rf_divider() as u64)
+    }
+
+    /// Actual output frequency, Hz, integer arithmetic only,
+    /// see `FracN::f_out_hz`.
+    pub fn f_out_hz(&self, ref_in_hz: u32) -> Result<u64, Error> {
+        FracN::f_out_hz(ref_in_hz, self)
+    }
+
+    /// Like `f_out_hz`, with REF IN from the reference path, see `Fpfd::with_ref_in`.
+    pub fn f_out_hz_ref_in(&self, refin: &RefIn) -> Result<u64, Error> {
+        FracN::f_out_hz_with(|rs| Fpfd::with_ref_in(refin, rs), self)
+    }
+}
+
+
+
+/// Cross-register consistency checks
+impl RegisterSet {
+
+    /// Checks register settings against datasheet restrictions
+    /// before they're written out, returns the first violation.
+    ///
+    /// * FRAC-N: MOD within 2 to 4095, FRAC and phase less than MOD.
+    /// * INT at or above the prescaler minimum.
+    /// * PFD frequency within FRAC-N/INT-N limits, REF IN within 30 MHz
+    ///   with the doubler (see `Fpfd::new`).
+    /// * Band select clock divider within 1 to 254 in the high mode.
+    /// * Cycle slip reduction needs the minimum charge pump current and
+    ///   a 50% duty cycle PFD input, i.e. the reference divide-by-2.
+    /// * R5 reserved bits DB20:DB19 set to 11.
+    /// * Lock detect function and precision paired for the mode, see `LockDetectConfig`.
+    pub fn validate(&self, ref_in_hz: u32) -> Result<(), Error> {
+        self.validate_with(|rs| Fpfd::new(ref_in_hz, rs))
+    }
+
+    /// Like `validate`, with REF IN from the reference path, see `Fpfd::with_ref_in`.
+    pub fn validate_ref_in(&self, refin: &RefIn) -> Result<(), Error> {
+        self.validate_with(|rs| Fpfd::with_ref_in(refin, rs))
+    }
+
+    fn validate_with<F>(&self, fpfd: F) -> Result<(), Error>
+    where F: FnOnce(&RegisterSet) -> Result<Fpfd,Error>,
+    {
+        if !self.r5.reserved_bits_ok() {
+            return Err(Error::InvalidReservedBits);
+        }
+
+        let ld = LockDetectConfig::from_registers(self);
+        let frac : Frac = self.get();
+        if !ld.is_recommended() || (frac.0 != 0 && matches!(ld.ldf, Ldf::IntN)) {
+            return Err(Error::LockDetectMismatch);
+        }
+
+        if is_frac_n(self) {
+            let modulus : Mod = self.get();
+            Mod::new(modulus.0)?;
+            check_frac_phase(self)?;
+        }
+
+        let int : Int = self.get();
+        check_int(int.0 as u64, self.get())?;
+
+        fpfd(self)?;
+
+        let bscd : BandSelectClockDiv = self.get();
+        let bscm : BandSelectClockMode = self.get();
+        if bscd.0 == 0 || (matches!(bscm, BandSelectClockMode::High) && bscd.0 > BAND_SELECT_CLOCK_DIV_HIGH_MAX) {
+            return Err(Error::InvalidBandSelectClockDiv);
+        }
+
+        let csr : CycleSlipReduction = self.get();
+        if matches!(csr, CycleSlipReduction::Enabled) {
+            let cp : ChargePumpCurrent = self.get();
+            if cp.0 != 0 {
+                return Err(Error::CsrChargePumpNotMinimum);
+            }
+            let rdiv2 : Rdiv2 = self.get();
+            if !matches!(rdiv2, Rdiv2::Enabled) {
+                return Err(Error::CsrRequiresRdiv2);
+            }
+        }
+        Ok(())
+    }
+
+    /// Phase noise depends on the REF IN duty cycle (45% to 55% is fine)
+    /// with the doubler on in the low spur mode. The reference divide-by-2
+    /// (`Rdiv2::Enabled`) restores a 50% duty cycle at the PFD and clears this.
+    pub fn duty_cycle_sensitive(&self) -> bool {
+        let doubler : RefDoubler = self.get();
+        let noise : NoiseMode = self.get();
+        let rdiv2 : Rdiv2 = self.get();
+        matches!((doubler, noise, rdiv2), (RefDoubler::Enabled, NoiseMode::LowSpur, Rdiv2::Disabled))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vco_range_boundaries() {
+        // 34.375 MHz is 2.2 GHz divided by 64
+        assert_eq!(vco_f_and_rf_divider(OUT_FREQ_MIN).unwrap(), (VCO_FREQ_MIN, 6));
+        assert!(vco_f_and_rf_divider(OUT_FREQ_MIN - 1).is_err());
+        assert_eq!(vco_f_and_rf_divider(OUT_FREQ_MAX).unwrap(), (VCO_FREQ_MAX, 0));
+        assert!(vco_f_and_rf_divider(OUT_FREQ_MAX + 1).is_err());
+        assert!(vco_f_and_rf_divider(0).is_err());
+    }
+
+    #[test]
+    fn set_f_out_at_low_band_edge() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(Mod(4000)));
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+
+        let rs = fracn.set_f_out(OUT_FREQ_MIN, rs).unwrap();
+        let RfDividerSelect(rf_divider_select) = rs.get();
+        assert_eq!(rf_divider_select, RF_DIVIDER_SELECT_MAX);
+        assert_eq!(rs.f_out_hz(25_000_000), Ok(OUT_FREQ_MIN));
+        // divide-by-128 doesn't exist, must not wrap into the 3 bit field
+        assert_eq!(fracn.set_f_out(OUT_FREQ_MIN - 1, rs), Err(Error::InvalidOutputFrequency));
+    }
+
+    #[test]
+    fn modulus_strategies() {
+        let rs = FracN::init(RegisterSet::recommended_defaults());
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        let modulus_for = |rs: RegisterSet| -> u16 { let m : Mod = rs.get(); m.0 };
+
+        assert_eq!(modulus_for(fracn.set_f_out_with_strategy(100_000_000, &FixedMod(4000), rs).unwrap()), 4000);
+        assert_eq!(FixedMod(5000).modulus(25_000_000, 100_000_000), MOD_MAX);
+        assert_eq!(FixedMod(0).modulus(25_000_000, 100_000_000), MOD_MIN);
+        assert_eq!(modulus_for(fracn.set_f_out_with_strategy(100_000_000, &MaxMod, rs).unwrap()), MOD_MAX);
+
+        // 2.5001 GHz: offset 100 kHz, 25 MHz / GCD(25 MHz, 100 kHz) = 250
+        let opt = fracn.set_f_out_with_strategy(2_500_100_000, &OptimalMod, rs).unwrap();
+        assert_eq!(modulus_for(opt), 250);
+        assert_eq!(opt.f_out_hz(25_000_000), Ok(2_500_100_000));
+        assert_eq!(OptimalMod.modulus(0, 1), MOD_MAX);
+
+        // 1.0001 GHz, VCO 4.0004 GHz: fundamental feedback sees a 400 kHz offset,
+        // divided feedback the 100 kHz one
+        let opt = fracn.set_f_out_with_strategy(1_000_100_000, &OptimalMod, rs).unwrap();
+        assert_eq!(modulus_for(opt), 125);
+        let divided = rs.set(FeedbackSelect::Divided);
+        let opt = fracn.set_f_out_with_strategy(1_000_100_000, &OptimalMod, divided).unwrap();
+        assert_eq!(modulus_for(opt), 250);
+        assert_eq!(opt.f_out_hz(25_000_000), Ok(1_000_100_000));
+    }
+
+    #[test]
+    fn prescaler_follows_vco() {
+        // 2 GHz out is 4 GHz at the prescaler with divide-by-2
+        let (vcof, _) = vco_f_and_rf_divider(2_000_000_000).unwrap();
+        assert!(matches!(choose_prescaler(vcof), Pr1Prescaler::Pr89));
+        assert!(matches!(choose_prescaler(OUT_FREQ_P45_MAX), Pr1Prescaler::Pr45));
+    }
+
+    #[test]
+    fn prescaler_hysteresis() {
+        let guard = PRESCALER_GUARD_BAND_HZ;
+        let below = OUT_FREQ_P45_MAX - guard / 2;
+        assert!(matches!(choose_prescaler_hysteretic(below, Pr1Prescaler::Pr89, guard), Pr1Prescaler::Pr89));
+        assert!(matches!(choose_prescaler_hysteretic(below, Pr1Prescaler::Pr45, guard), Pr1Prescaler::Pr45));
+        assert!(matches!(choose_prescaler_hysteretic(OUT_FREQ_P45_MAX - guard, Pr1Prescaler::Pr89, guard), Pr1Prescaler::Pr45));
+        // 4/5 is never kept above 3.6 GHz
+        assert!(matches!(choose_prescaler_hysteretic(OUT_FREQ_P45_MAX + 1, Pr1Prescaler::Pr45, guard), Pr1Prescaler::Pr89));
+    }
+
+    #[test]
+    fn prescaler_at_output_limits() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(Mod(4000)));
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        let prescaler_for = |f_out_hz| -> Pr1Prescaler { fracn.set_f_out(f_out_hz, rs).unwrap().get() };
+
+        assert!(matches!(prescaler_for(max_out_freq(Pr1Prescaler::Pr45)), Pr1Prescaler::Pr45));
+        assert!(matches!(prescaler_for(max_out_freq(Pr1Prescaler::Pr45) + 1), Pr1Prescaler::Pr89));
+        assert!(matches!(prescaler_for(max_out_freq(Pr1Prescaler::Pr89)), Pr1Prescaler::Pr89));
+        // 1.7 GHz is 3.4 GHz at the VCO
+        assert!(matches!(prescaler_for(1_700_000_000), Pr1Prescaler::Pr45));
+    }
+
+    #[test]
+    fn validate_catches_bad_combinations() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(BandSelectClockDiv(200)).set(Mod(4000)));
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        let rs = fracn.set_f_out(100_000_000, rs).unwrap();
+        assert!(rs.validate(25_000_000).is_ok());
+
+        assert_eq!(rs.set(Mod(4096)).validate(25_000_000), Err(Error::InvalidModulus));
+        assert_eq!(rs.set(Mod(4000)).set(Frac(4000)).validate(25_000_000), Err(Error::InvalidFrac));
+        assert_eq!(rs.set(RefDoubler::Enabled).validate(50_000_000), Err(Error::DoublerInputTooHigh));
+        assert_eq!(rs.set(R(0)).validate(25_000_000), Err(Error::InvalidRCounter));
+        assert_eq!(Fpfd::new(25_000_000, &RegisterSet::default()).err(), Some(Error::InvalidRCounter));
+        assert_eq!(rs.set(CycleSlipReduction::Enabled).validate(25_000_000), Err(Error::CsrChargePumpNotMinimum));
+
+        assert_eq!(rs.set(Ldp::Ldp6ns).validate(25_000_000), Err(Error::LockDetectMismatch));
+        assert_eq!(LockDetectConfig::int_n().apply(rs.set(Frac(1))).validate(25_000_000), Err(Error::LockDetectMismatch));
+        assert!(LockDetectConfig::int_n().apply(rs).validate(25_000_000).is_ok());
+
+        let mut cleared = rs;
+        cleared.r5.w &= !R5_RESERVED_BITS;
+        assert_eq!(cleared.validate(25_000_000), Err(Error::InvalidReservedBits));
+        assert!(RegisterSet::default().r5.reserved_bits_ok());
+    }
+
+    #[test]
+    fn duty_cycle_sensitivity() {
+        let rs = RegisterSet::recommended_defaults().set(RefDoubler::Enabled).set(NoiseMode::LowSpur);
+        assert!(rs.duty_cycle_sensitive());
+        assert!(!rs.set(Rdiv2::Enabled).duty_cycle_sensitive());
+        assert!(!rs.set(NoiseMode::LowNoise).duty_cycle_sensitive());
+        assert!(!rs.set(RefDoubler::Disabled).duty_cycle_sensitive());
+    }
+
+    #[test]
+    fn with_reference_sets_pfd() {
+        let refin = RefIn::new(25_000_000, true, 5, true).unwrap();
+        let rs = RegisterSet::default().with_reference(&refin);
+        assert_eq!(rs.f_pfd_hz(25_000_000), Ok(refin.f_pfd_hz()));
+        let r : R = rs.get();
+        assert_eq!(r.0, 5);
+    }
+
+    #[test]
+    fn frac_params_of_example() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(Mod(4000)));
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        // 100 MHz is 3.2 GHz / 32, N = 128
+        let rs = fracn.set_f_out(100_000_000, rs).unwrap();
+        assert_eq!(rs.frac_params(), (128, 0, 4000, 32));
+    }
+
+    #[test]
+    fn band_select_time() {
+        // 25 MHz / 200 = 125 kHz, 10 cycles of 8 µs
+        let rs = RegisterSet::recommended_defaults().set(BandSelectClockDiv(200));
+        assert_eq!(rs.band_select_time_us(25_000_000), Ok(80));
+        assert_eq!(rs.set(BandSelectClockDiv(0)).band_select_time_us(25_000_000), Err(Error::InvalidBandSelectClockDiv));
+    }
+
+    #[test]
+    fn fast_lock_checks_band_select_div() {
+        let rs = RegisterSet::recommended_defaults().set(BandSelectClockDiv(255));
+        assert_eq!(FracN::init_optimized(rs, Optimization::FastLock), Err(Error::InvalidBandSelectClockDiv));
+        assert!(FracN::init_optimized(rs, Optimization::LowNoise).is_ok());
+        assert!(FracN::init_optimized(rs.set(BandSelectClockDiv(254)), Optimization::FastLock).is_ok());
+    }
+
+    #[test]
+    fn registers_for_one_shot() {
+        let rs = registers_for(25_000_000, 1_000_000_000).unwrap();
+        assert_eq!(rs.f_out_hz(25_000_000), Ok(1_000_000_000));
+
+        // 100 MHz REF IN is divided by 4 to 25 MHz
+        let rs = registers_for(100_000_000, 1_000_100_000).unwrap();
+        assert_eq!(rs.f_pfd_hz(100_000_000), Ok(25_000_000));
+        assert_eq!(rs.f_out_hz(100_000_000), Ok(1_000_100_000));
+
+        assert_eq!(registers_for(25_000_000, 10_000_000), Err(Error::InvalidOutputFrequency));
+
+        let rs = registers_for_pfd(25_000_000, 5_000_000, 1_000_100_000).unwrap();
+        assert_eq!(rs.f_pfd_hz(25_000_000), Ok(5_000_000));
+        assert_eq!(rs.f_out_hz(25_000_000), Ok(1_000_100_000));
+        assert_eq!(registers_for_pfd(25_000_000, 40_000_000, 1_000_000_000), Err(Error::PfdTooHighForFracN));
+    }
+
+    #[test]
+    fn nearest_reports_residual() {
+        let refin = RefIn::new(25_000_000, false, 1, false).unwrap();
+
+        let (plan, err) = FrequencyPlan::nearest(&refin, 2_500_100_000).unwrap();
+        assert_eq!((plan.achieved_hz, err), (2_500_100_000, 0));
+
+        // 25 MHz / 4095 steps at the VCO can't hit 1 Hz offsets
+        let (plan, err) = FrequencyPlan::nearest(&refin, 3_000_000_001).unwrap();
+        assert_eq!(plan.achieved_hz as i64 - 3_000_000_001, err);
+        assert!(err.abs() < 25_000_000 / 4095);
+
+        let (plan, err) = FrequencyPlan::nearest(&refin, 1_000_000).unwrap();
+        assert_eq!((plan.achieved_hz, err), (OUT_FREQ_MIN, (OUT_FREQ_MIN - 1_000_000) as i64));
+    }
+
+    #[test]
+    fn divided_feedback() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(Mod(4000))).set(FeedbackSelect::Divided);
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+
+        // 1.1 GHz: VCO 2.2 GHz, N counter sees 1.1 GHz, N = 44
+        let rs = fracn.set_f_out(1_100_000_000, rs).unwrap();
+        assert!(matches!(rs.get(), FeedbackSelect::Divided));
+        assert_eq!(rs.frac_params(), (44, 0, 4000, 2));
+        assert_eq!(rs.f_out_hz(25_000_000), Ok(1_100_000_000));
+        assert_eq!(rs.vco_freq_hz(25_000_000), Ok(2_200_000_000));
+
+        let rs = IntN(fracn.0).set_f_out(1_100_000_000, IntN::init(rs).set(FeedbackSelect::Divided)).unwrap();
+        assert_eq!(rs.frac_params(), (44, 0, 1, 2));
+
+        // N = 100 MHz / 25 MHz is below the INT minimum
+        assert_eq!(fracn.set_f_out(100_000_000, rs.set(Mod(4000))), Err(Error::IntTooLow));
+
+        let refin = RefIn::new(25_000_000, false, 1, false).unwrap();
+        let options = PlanOptions { feedback: FeedbackSelect::Divided, ..PlanOptions::default() };
+        let plan = FrequencyPlan::solve(&refin, 1_100_000_000, options).unwrap();
+        assert_eq!((plan.int, plan.achieved_hz), (44, 1_100_000_000));
+    }
+
+    #[test]
+    fn int_n_rejects_int_above_16_bits() {
+        // 10 MHz / (1023 × 2) = 4887 Hz PFD
+        let rs = IntN::init(RegisterSet::recommended_defaults().set(R(1023)).set(Rdiv2::Enabled));
+        let intn = IntN(Fpfd::new(10_000_000, &rs).unwrap());
+        assert_eq!(intn.0.0, 4887);
+        assert_eq!(intn.set_f_out(4887 * 450_174, rs), Err(Error::IntTooHigh));
+        let plan = FrequencyPlan::with_fpfd(intn.0, 2_200_000_000, PlanOptions::default());
+        assert_eq!(plan.map(|p| p.int), Err(Error::IntTooHigh));
+    }
+
+    #[test]
+    fn square_wave_reference_end_to_end() {
+        let refin = RefIn::new_square_wave(5_000_000, false, 1, false).unwrap();
+        let plan = FrequencyPlan::solve(&refin, 2_500_000_000, PlanOptions::default()).unwrap();
+        let (bscd, bscm) = band_select_clock_div(refin.f_pfd_hz());
+        let rs = FracN::init(RegisterSet::recommended_defaults().with_reference(&refin)).set(bscd).set(bscm);
+        let rs = plan.apply(rs).unwrap();
+
+        assert_eq!(rs.validate_ref_in(&refin), Ok(()));
+        assert_eq!(rs.f_out_hz_ref_in(&refin), Ok(2_500_000_000));
+        assert_eq!(Fpfd::with_ref_in(&refin, &rs).map(|f| f.0), Ok(5_000_000));
+        // a sine wave can't go below 10 MHz
+        assert_eq!(rs.validate(5_000_000), Err(Error::InvalidReferenceFrequency));
+    }
+
+    #[test]
+    fn retune_skips_band_select_for_small_steps() {
+        let rs = FracN::init(RegisterSet::recommended_defaults().set(Mod(4000)));
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        let rs = fracn.set_f_out(1_000_000_000, rs).unwrap();
+
+        // 500 kHz: FRAC changes, phase adjust skips band select
+        let (small, band_select) = fracn.retune(1_000_000_000, 1_000_500_000, BAND_SELECT_SKIP_MAX_HZ, rs).unwrap();
+        assert!(!band_select);
+        assert!(matches!(small.get(), Ph1PhaseAdj::On));
+        assert_eq!(small.f_out_hz(25_000_000), Ok(1_000_500_000));
+        assert_eq!(small.diff(&rs), [true, true, false, false, false, false]);
+
+        // 50 MHz, same RF divider: band select runs, phase adjust stays off
+        let (large, band_select) = fracn.retune(1_000_000_000, 1_050_000_000, BAND_SELECT_SKIP_MAX_HZ, rs).unwrap();
+        assert!(band_select);
+        assert!(matches!(large.get(), Ph1PhaseAdj::Off));
+        assert_eq!(large.diff(&rs), [true, false, false, false, false, false]);
+
+        // a small step that switches the RF divider still needs band select
+        let (_, band_select) = fracn.retune(2_200_000_000, 2_199_900_000, BAND_SELECT_SKIP_MAX_HZ, rs.set(RfDividerSelect(0))).unwrap();
+        assert!(band_select);
+    }
+
+    #[test]
+    fn hertz_frequencies() {
+        let refin = RefIn::new_hertz(Hertz::mhz(25), false, 1, false).unwrap();
+        let rs = FracN::init(RegisterSet::recommended_defaults().with_reference(&refin).set(Mod(4000)));
+        let fracn = FracN(Fpfd::new_hertz(Hertz::mhz(25), &rs).unwrap());
+        assert_eq!(fracn.set_f_out(Hertz::mhz(1_000), rs), fracn.set_f_out(1_000_000_000, rs));
+
+        let too_high = Hertz::hz(u32::MAX as u64 + 1);
+        assert_eq!(RefIn::new_hertz(too_high, false, 1, false).err(), Some(Error::InvalidReferenceFrequency));
+        assert_eq!(Fpfd::new_hertz(too_high, &rs).err(), Some(Error::InvalidReferenceFrequency));
+        assert_eq!(u32::try_from(too_high), Err(Error::FrequencyOutOfRange));
+    }
+
+    #[test]
+    fn refin_doubler_limit() {
+        assert_eq!(RefIn::new(30_000_001, true, 1, false).err(), Some(Error::DoublerInputTooHigh));
+        assert_eq!(RefIn::new(30_000_000, true, 1, false).map(|r| r.f_pfd_hz()), Ok(60_000_000));
+        assert!(RefIn::new(30_000_001, false, 1, false).is_ok());
+    }
+
+    #[test]
+    fn refin_rejects_zero_r() {
+        assert_eq!(RefIn::new(25_000_000, false, 0, false).err(), Some(Error::InvalidRCounter));
+        assert_eq!(RefIn::new_square_wave(5_000_000, false, 0, false).err(), Some(Error::InvalidRCounter));
+
+        // fields are public, R = 0 saturates to 1 instead of dividing by zero
+        let refin = RefIn { f_hz: 25_000_000, doubler: false, r: 0, rdiv2: false, square_wave: false, };
+        assert_eq!(refin.f_pfd_hz(), 25_000_000);
+        assert_eq!(refin.validate(), Err(Error::InvalidRCounter));
+        assert_eq!(FrequencyPlan::solve(&refin, 1_000_000_000, PlanOptions::default()).err(), Some(Error::InvalidRCounter));
+    }
+
+    #[test]
+    fn pfd_limits_per_mode() {
+        let rs = RegisterSet::default().set(R(2));
+        assert_eq!(Fpfd::new(100_000_000, &FracN::init(rs)).err(), Some(Error::PfdTooHighForFracN));
+
+        // INT-N: 45 MHz with band select, 90 MHz with phase adjust on
+        let rs = IntN::init(rs);
+        assert_eq!(Fpfd::new(100_000_000, &rs).err(), Some(Error::InvalidPfdFrequency));
+        assert_eq!(Fpfd::new(90_000_000, &rs).map(|f| f.0), Ok(45_000_000));
+        let rs = rs.set(Ph1PhaseAdj::On);
+        assert_eq!(Fpfd::new(180_000_000, &rs).map(|f| f.0), Ok(90_000_000));
+        assert_eq!(Fpfd::new(182_000_000, &rs).err(), Some(Error::InvalidPfdFrequency));
+    }
+
+    #[test]
+    fn optimal_reference_within_pfd_limit() {
+        // closest R = 1 would be a 100 MHz PFD
+        let refin = RefIn::optimal(100_000_000, 89_000_000).unwrap();
+        assert_eq!((refin.r, refin.f_pfd_hz()), (2, 50_000_000));
+        assert!(Fpfd::new(100_000_000, &IntN::init(RegisterSet::default().with_reference(&refin)).set(Ph1PhaseAdj::On)).is_ok());
+
+        // 8 MHz can't be hit exactly, 25 MHz × 2 / 7 is the closest below
+        let refin = RefIn::optimal(25_000_000, 8_000_000).unwrap();
+        assert_eq!((refin.doubler, refin.r, refin.rdiv2, refin.f_pfd_hz()), (true, 7, false, 7_142_857));
+
+        // FRAC-N caps a 40 MHz target at 32 MHz, 100 MHz / 4
+        let refin = RefIn::optimal_for_mode(100_000_000, 40_000_000, SynthMode::FractionalN).unwrap();
+        assert_eq!(refin.f_pfd_hz(), 25_000_000);
+        assert_eq!(RefIn::optimal(100_000_000, 40_000_000).unwrap().f_pfd_hz(), 33_333_333);
+
+        // even R = 1023 is above a 1 kHz target
+        assert_eq!(RefIn::optimal(250_000_000, 1_000).err(), Some(Error::InvalidPfdFrequency));
+    }
+
+    #[test]
+    fn set_reference_for_target_pfd() {
+        let rs = RegisterSet::recommended_defaults().set_reference(25_000_000, 25_000_000).unwrap();
+        assert_eq!(rs.f_pfd_hz(25_000_000), Ok(25_000_000));
+
+        // 250 MHz / 8 = 31.25 MHz, 250 MHz / 7 would be above 32 MHz
+        let rs = RegisterSet::recommended_defaults().set_reference(250_000_000, 32_000_000).unwrap();
+        assert_eq!(rs.f_pfd_hz(250_000_000), Ok(31_250_000));
+
+        assert_eq!(RegisterSet::default().set_reference(25_000_000, 33_000_000), Err(Error::PfdTooHighForFracN));
+        assert_eq!(RegisterSet::default().set_reference(1_000_000, 1_000_000), Err(Error::InvalidReferenceFrequency));
+    }
+
+    #[test]
+    fn spur_offsets_follow_reduced_mod() {
+        let refin = RefIn::new(25_000_000, false, 1, false).unwrap();
+
+        // 2.5001 GHz: FRAC/MOD = 1/250, spurs every 100 kHz
+        let plan = FrequencyPlan::solve(&refin, 2_500_100_000, PlanOptions::default()).unwrap();
+        assert_eq!(plan.spur_offsets::<3>(), [100_000, 200_000, 300_000]);
+        assert_eq!(plan.integer_boundary_spur_hz(), Some(100_000));
+
+        // FRAC/MOD = 2000/4000 reduces to 1/2, 1.25 GHz out is VCO / 2
+        let options = PlanOptions { modulus: Some(4000), integer_n: false, ..PlanOptions::default() };
+        let plan = FrequencyPlan::solve(&refin, 1_256_250_000, options).unwrap();
+        assert_eq!((plan.frac, plan.modulus, plan.rf_divider_select), (2000, 4000, 1));
+        assert_eq!(plan.spur_offsets::<2>(), [6_250_000, 12_500_000]);
+        assert_eq!(plan.integer_boundary_spur_hz(), Some(6_250_000));
+
+        // INT-N: reference spurs only
+        let plan = FrequencyPlan::solve(&refin, 2_500_000_000, PlanOptions::default()).unwrap();
+        assert_eq!(plan.spur_offsets::<1>(), [25_000_000]);
+        assert_eq!(plan.integer_boundary_spur_hz(), None);
+    }
+
+    #[test]
+    fn plan_picks_integer_n() {
+        let refin = RefIn::new(25_000_000, false, 1, false).unwrap();
+
+        let plan = FrequencyPlan::solve(&refin, 2_500_000_000, PlanOptions::default()).unwrap();
+        assert!(plan.is_integer_n());
+        assert_eq!((plan.int, plan.frac, plan.modulus, plan.achieved_hz), (100, 0, 1, 2_500_000_000));
+
+        let plan = FrequencyPlan::solve(&refin, 2_500_100_000, PlanOptions::default()).unwrap();
+        assert!(!plan.is_integer_n());
+        assert_eq!(plan.achieved_hz, 2_500_100_000);
+    }
+}
@@ -1,9 +1,13 @@
 //! Constants
 
-/// Minimum allowed REFin frequency
+use crate::register::Pr1Prescaler;
+
+/// Minimum allowed REFin frequency, inclusive.
+/// Lower frequencies need a DC-coupled CMOS square wave
+/// with a slew rate above 21 V/µs, see `RefIn::new_square_wave`.
 pub const REF_IN_FREQ_MIN: u32 = 10_000_000;
 
-/// Maximum allowed REFin frequency
+/// Maximum allowed REFin frequency, inclusive
 pub const REF_IN_FREQ_MAX: u32 = 250_000_000;
 
 /// Max Phase Detector Frequency (fractional N mode)
@@ -28,6 +32,9 @@ pub const VCO_FREQ_MIN: u64 = 2_200_000_000;
 /// Fundamental VCO mode (before dividers), max frequency
 pub const VCO_FREQ_MAX: u64 = 4_400_000_000;
 
+/// Maximum RF divider select, divide-by-64
+pub const RF_DIVIDER_SELECT_MAX: u8 = 6;
+
 /// Minimum allowed output frequency
 /// 2200 MHz fundamental output and divide-by-64 selected
 pub const OUT_FREQ_MIN: u64 = VCO_FREQ_MIN / 64;
@@ -39,4 +46,92 @@ pub const OUT_FREQ_MAX: u64 = VCO_FREQ_MAX;
 /// 4/5, the maximum RF frequency allowed is 3.6 GHz. Therefore,
 /// when operating the ADF4351 above 3.6 GHz, the prescaler must
 /// be set to 8/9.
+/// This is the prescaler input, i.e. the VCO frequency.
 pub const OUT_FREQ_P45_MAX: u64 = 3_600_000_000;
+
+/// Charge pump current at the maximum setting (0b1111), µA.
+/// Assumes RSET = 5.1 kΩ, the 16 settings are equally spaced
+/// from CP_CURRENT_MAX_UA/16 up to CP_CURRENT_MAX_UA.
+pub const CP_CURRENT_MAX_UA: u32 = 5_000;
+
+/// Minimum fractional modulus (MOD) value
+pub const MOD_MIN: u16 = 2;
+
+/// Maximum fractional modulus (MOD) value, 12 bits
+pub const MOD_MAX: u16 = 4095;
+
+/// Max band select logic clock, low (slow) band select clock mode
+pub const BAND_SELECT_CLOCK_MAX: u32 = 125_000;
+
+/// Max band select logic clock, high (fast) band select clock mode
+pub const BAND_SELECT_CLOCK_HIGH_MAX: u32 = 500_000;
+
+/// Max band select clock divider in the high band select clock mode
+pub const BAND_SELECT_CLOCK_DIV_HIGH_MAX: u8 = 254;
+
+/// Band select clock cycles taken by VCO band selection
+pub const BAND_SELECT_CYCLES: u64 = 10;
+
+/// Minimum INT value with the 4/5 prescaler
+pub const INT_P45_MIN: u16 = 23;
+
+/// Minimum INT value with the 8/9 prescaler
+pub const INT_P89_MIN: u16 = 75;
+
+/// Minimum INT value for the prescaler
+pub const fn int_min(p: Pr1Prescaler) -> u16 {
+    match p {
+        Pr1Prescaler::Pr45 => INT_P45_MIN,
+        Pr1Prescaler::Pr89 => INT_P89_MIN,
+    }
+}
+
+/// Maximum output frequency with the prescaler, Hz.
+/// 4/5 tops out at 3.6 GHz at the VCO, which is also the highest
+/// output frequency with it (no RF divider), 8/9 covers the whole VCO range.
+pub const fn max_out_freq(prescaler: Pr1Prescaler) -> u64 {
+    match prescaler {
+        Pr1Prescaler::Pr45 => OUT_FREQ_P45_MAX,
+        Pr1Prescaler::Pr89 => OUT_FREQ_MAX,
+    }
+}
+
+/// Minimum output frequency reachable with the prescaler and PFD frequency, Hz.
+/// VCO can't go below INT min × f PFD, `None` if that's above the VCO range.
+pub const fn out_freq_min(p: Pr1Prescaler, fpfd_hz: u32) -> Option<u64> {
+    let vco_int_min = int_min(p) as u64 * fpfd_hz as u64;
+    let vco_min = if vco_int_min > VCO_FREQ_MIN { vco_int_min } else { VCO_FREQ_MIN };
+    if vco_min > VCO_FREQ_MAX {
+        None
+    } else {
+        Some(vco_min.div_ceil(1 << RF_DIVIDER_SELECT_MAX))
+    }
+}
+
+/// Control bits (C3:C1) select the destination register of a word
+pub const CONTROL_BITS_MASK: u32 = 0b111;
+
+/// Reserved R5 bits DB20:DB19, must be set to 11
+pub const R5_RESERVED_BITS: u32 = 0b11 << 19;
+
+/// Minimum R counter value
+pub const R_MIN: u16 = 1;
+
+/// Maximum R counter value, 10 bits
+pub const R_MAX: u16 = 1023;
+
+/// Maximum REFin frequency with the reference doubler enabled
+pub const REF_IN_DOUBLER_FREQ_MAX: u32 = 30_000_000;
+
+/// Frequency change below which VCO band selection can be skipped, Hz
+pub const BAND_SELECT_SKIP_MAX_HZ: u64 = 1_000_000;
+
+/// Default guard band below 3.6 GHz where the 8/9 prescaler is kept
+/// when sweeping down, see `config::choose_prescaler_hysteretic`, Hz
+pub const PRESCALER_GUARD_BAND_HZ: u64 = 20_000_000;
+
+/// Maximum clock divider value, 12 bits
+pub const CLOCK_DIVIDER_MAX: u16 = 4095;
+
+/// Length of `RegisterSet::to_adi_hex` text, six `0x%08X` lines
+pub const ADI_HEX_LEN: usize = 6 * 11;
@@ -1,56 +1,577 @@
 //! Device pins
 
+use core::marker::PhantomData;
+
 use embedded_hal:: {
-    digital::v2::OutputPin,
-    blocking::{ delay::*, spi::*, },
+    digital::{ self, InputPin, OutputPin, },
+    delay::DelayNs,
+    spi::{ self, Operation, SpiBus, SpiDevice, },
 };
 
 
+use crate::config::*;
+use crate::constants::*;
 use crate::errors::*;
 use crate::register::*;
 
+/// SPI peripheral bit order.
+/// ADF4351 expects each word MSB first (SPI mode 0, data latched on the
+/// rising CLK edge), peripherals that can only shift LSB first need
+/// the bits of each byte reversed in software.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BitOrder {
+    /// Peripheral shifts bytes out MSB first, default
+    MsbFirst,
+    /// Peripheral shifts bytes out LSB first
+    LsbFirst,
+}
+
+/// `BitOrder::MsbFirst`
+impl Default for BitOrder {
+    fn default() -> Self {
+        BitOrder::MsbFirst
+    }
+}
+
+/// Register word bytes for the peripheral bit order,
+/// so that it reaches the device MSB first: big endian bytes,
+/// bits of each byte reversed for `BitOrder::LsbFirst`.
+#[inline(always)]
+pub(crate) fn register_bytes(w: u32, bit_order: BitOrder) -> [u8; 4] {
+    let bytes = [
+        ((w >> 24) & 0xFF ) as u8,
+        ((w >> 16) & 0xFF ) as u8,
+        ((w >>  8) & 0xFF ) as u8,
+        ( w        & 0xFF ) as u8,
+    ];
+    match bit_order {
+        BitOrder::MsbFirst => bytes,
+        BitOrder::LsbFirst => bytes.map(u8::reverse_bits),
+    }
+}
+
+
+/// Load enable (LE) timings around a register write, ns.
+/// Datasheet minimums are in the 10 ns range, HALs with coarser
+/// `DelayNs` implementations round them up.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timings {
+    /// From the end of the SPI transfer to LE rising edge
+    pub le_setup_ns: u32,
+    /// LE high time
+    pub le_pulse_ns: u32,
+    /// From LE falling edge to the next SPI transfer
+    pub le_hold_ns: u32,
+}
+
+/// Default LE timings, 5/10/5 µs, plenty for typical boards
+pub const DEFAULT_TIMINGS: Timings = Timings { le_setup_ns: 5_000, le_pulse_ns: 10_000, le_hold_ns: 5_000, };
+
+/// Minimum LE pulse width (t7), ns
+pub const LE_PULSE_MIN_NS: u32 = 20;
+
+impl Timings {
+    /// Checks the LE pulse against the datasheet minimum (20 ns),
+    /// shorter pulses may not latch the shift register.
+    pub fn new(le_setup_ns: u32, le_pulse_ns: u32, le_hold_ns: u32) -> Result<Self, Error> {
+        let timings = Timings { le_setup_ns, le_pulse_ns, le_hold_ns, };
+        timings.validate().map(|_| timings)
+    }
+
+    /// Checks the LE pulse against the datasheet minimum, see `new`.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.le_pulse_ns < LE_PULSE_MIN_NS {
+            return Err(Error::InvalidTiming);
+        }
+        Ok(())
+    }
+}
+
+/// Step of the LE pulse that latches a register word after it's clocked out
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub(crate) enum LatchStep {
+    /// Wait, ns
+    Delay(u32),
+    /// Set LE high (true) or low
+    Le(bool),
+}
+
+impl Timings {
+    /// LE pulse sequence shared by the blocking, async and group drivers:
+    /// setup, LE high, pulse width, LE low, hold.
+    pub(crate) fn latch_sequence(&self) -> [LatchStep; 5] {
+        [
+            LatchStep::Delay(self.le_setup_ns),
+            LatchStep::Le(true),
+            LatchStep::Delay(self.le_pulse_ns),
+            LatchStep::Le(false),
+            LatchStep::Delay(self.le_hold_ns),
+        ]
+    }
+}
+
+/// `DEFAULT_TIMINGS`
+impl Default for Timings {
+    fn default() -> Self {
+        DEFAULT_TIMINGS
+    }
+}
+
+
+/// Register write sequence, see `Adf4351::write_register_set_order`
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WriteOrder {
+    /// All registers, R5 to R0, required after power-up
+    PowerUp,
+    /// Registers that changed since the last write, R5 to R1 order, then R0.
+    /// The R0 write applies double buffered settings and, unless phase adjust
+    /// is on, re-runs VCO band selection.
+    FracUpdate,
+}
+
+
+/// MUXOUT pin is not connected
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NoMuxout;
+
+
+/// LE driven by the chip select of an `SpiDevice`, see `SpiDeviceLe`.
+/// Setting it does nothing, errors are typed to match the CE pin.
+pub struct LeAsCs<E>(PhantomData<E>);
+
+impl<E> LeAsCs<E> {
+    /// New placeholder LE pin
+    pub const fn new() -> Self {
+        LeAsCs(PhantomData)
+    }
+}
+
+impl<E> Default for LeAsCs<E> {
+    fn default() -> Self {
+        LeAsCs::new()
+    }
+}
+
+impl<E: digital::Error> digital::ErrorType for LeAsCs<E> {
+    type Error = E;
+}
+
+impl<E: digital::Error> OutputPin for LeAsCs<E> {
+    fn set_low(&mut self) -> Result<(), E> { Ok(()) }
+    fn set_high(&mut self) -> Result<(), E> { Ok(()) }
+}
+
+
+/// `SpiDevice` with its chip select wired to LE, as an `SpiBus`,
+/// for buses shared with other peripherals.
+///
+/// Each register write is a single transaction: CS (LE) goes low, the
+/// word shifts in, `le_setup_ns` passes, then CS deasserts and the rising
+/// LE edge latches the word. This differs from a true chip select:
+/// * DATA/CLK aren't gated, traffic for other devices on the bus shifts into
+///   the ADF4351 too. It's harmless, only the last 32 bits before the LE
+///   rising edge are latched, and each write shifts a whole word.
+/// * LE idles high rather than low between transactions.
+/// * LE pulse width is the idle time between transactions, which
+///   `Adf4351::write_register` still guarantees with `Timings::le_pulse_ns`.
+/// * LE setup is fixed at construction, `Adf4351::with_timings` doesn't change it.
+pub struct SpiDeviceLe<D> {
+    device: D,
+    le_setup_ns: u32,
+}
+
+impl<D> SpiDeviceLe<D> {
+    /// Wraps an `SpiDevice`, `le_setup_ns` is the delay from the last
+    /// clock edge to CS deassertion (LE rising edge).
+    pub fn new(device: D, le_setup_ns: u32) -> Self {
+        SpiDeviceLe { device, le_setup_ns, }
+    }
+
+    /// Releases the wrapped `SpiDevice`
+    pub fn release(self) -> D {
+        self.device
+    }
+}
+
+impl<D: SpiDevice<u8>> spi::ErrorType for SpiDeviceLe<D> {
+    type Error = D::Error;
+}
+
+impl<D: SpiDevice<u8>> SpiBus<u8> for SpiDeviceLe<D> {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.device.transaction(&mut [Operation::Read(words), Operation::DelayNs(self.le_setup_ns)])
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.device.transaction(&mut [Operation::Write(words), Operation::DelayNs(self.le_setup_ns)])
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.device.transaction(&mut [Operation::Transfer(read, write), Operation::DelayNs(self.le_setup_ns)])
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.device.transaction(&mut [Operation::TransferInPlace(words), Operation::DelayNs(self.le_setup_ns)])
+    }
+
+    /// Transactions complete before returning
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+
+/// Digital lock detect polling interval, µs
+const LOCK_POLL_US: u32 = 10;
+
+/// Consecutive high MUXOUT reads required for analog lock detect
+const ANALOG_LOCK_SAMPLES: u32 = 8;
+
+
 /// ADF4351 device
-pub struct Adf4351<CE, LE, SPI> {
+pub struct Adf4351<CE, LE, SPI, MUX = NoMuxout> {
     spi: SPI,
     pin_ce: CE,
     pin_le: LE,
+    pin_mux: MUX,
+    timings: Timings,
+    bit_order: BitOrder,
+    muxout: Muxout,
+    current: Option<RegisterSet>,
 }
 
 
 impl<CE, LE, SPI,> Adf4351<CE, LE, SPI,>
 where CE: OutputPin,
-      LE: OutputPin,
-      SPI: Write<u8>,
+      LE: OutputPin<Error = CE::Error>,
+      SPI: SpiBus<u8>,
 {
     /// Creates the device (unconfigured, no output).
     ///
-    /// `spi` - SPI device (`MOSI` => `DATA`, `CLK` => `CLK`, `CPHA` = 0)
+    /// `spi` - SPI bus (`MOSI` => `DATA`, `CLK` => `CLK`, `CPHA` = 0)
     /// `pin_ce` - "chip enable" pin
-    /// `pin_le` - "load enable" pin
+    /// `pin_le` - "load enable" pin, same error type as `pin_ce`, see `with_muxout`
     ///
+    /// See `eh02` module (`eh02` feature) for embedded-hal 0.2 peripherals.
     pub fn new(
         spi: SPI,
         pin_ce: CE,
         pin_le: LE,
     ) -> Self {
-        Adf4351 { spi, pin_ce, pin_le, }
+        Adf4351 { spi, pin_ce, pin_le, pin_mux: NoMuxout, timings: Timings::default(), bit_order: BitOrder::default(), muxout: Muxout::ThreeStateOut, current: None, }
+    }
+}
+
+
+impl<CE, D> Adf4351<CE, LeAsCs<CE::Error>, SpiDeviceLe<D>>
+where CE: OutputPin,
+      D: SpiDevice<u8>,
+{
+    /// Creates the device on a shared bus, the `SpiDevice` chip select
+    /// drives LE (see `SpiDeviceLe` for how that differs from a true CS).
+    /// LE setup is `DEFAULT_TIMINGS.le_setup_ns`, use
+    /// `Adf4351::new(SpiDeviceLe::new(spi_device, le_setup_ns), pin_ce, LeAsCs::new())`
+    /// for a different one.
+    ///
+    /// `spi_device` - SPI device (`MOSI` => `DATA`, `CLK` => `CLK`, `CS` => `LE`, `CPHA` = 0)
+    /// `pin_ce` - "chip enable" pin
+    pub fn new_with_spi_device(
+        spi_device: D,
+        pin_ce: CE,
+    ) -> Self {
+        Adf4351::new(SpiDeviceLe::new(spi_device, DEFAULT_TIMINGS.le_setup_ns), pin_ce, LeAsCs::new())
+    }
+}
+
+
+impl<CE, LE, SPI, MUX> Adf4351<CE, LE, SPI, MUX>
+where CE: OutputPin,
+      LE: OutputPin<Error = CE::Error>,
+      SPI: SpiBus<u8>,
+{
+    /// Adds an input pin connected to MUXOUT.
+    ///
+    /// **The pin has to have the same error type as CE (and LE)**, pin errors
+    /// are reported through a single `DeviceError::Pin`. That's the case for
+    /// HALs with infallible pins (`core::convert::Infallible`), for HALs with
+    /// per-port or input/output specific error types wrap the pins so that
+    /// their errors map to a common type.
+    pub fn with_muxout<M>(self, pin_mux: M) -> Adf4351<CE, LE, SPI, M>
+    where M: InputPin<Error = CE::Error>,
+    {
+        let Adf4351 { spi, pin_ce, pin_le, timings, bit_order, muxout, current, .. } = self;
+        Adf4351 { spi, pin_ce, pin_le, pin_mux, timings, bit_order, muxout, current, }
+    }
+
+    /// Replaces default LE timings, e.g. for slow or isolated SPI links.
+    /// `Error::InvalidTiming` for an LE pulse below `LE_PULSE_MIN_NS`.
+    pub fn with_timings(mut self, timings: Timings) -> Result<Self, Error> {
+        timings.validate()?;
+        self.timings = timings;
+        Ok(self)
+    }
+
+    /// Sets the SPI peripheral bit order, for peripherals stuck LSB first.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
     }
 
     /// Writes all control registers out.
     /// Blocking call.
     pub fn write_register_set<Delay>(
-        self: &mut Self,
+        &mut self,
         delay: &mut Delay,
         rs: &RegisterSet,
-    ) -> Result<(), Error>
-    where Delay: DelayUs<u16>,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
     {
-        for r in rs.to_words().iter().rev() {
-            self.write_register(delay, *r)?;
+        let mut words = rs.to_words();
+        words.reverse();
+        self.write_words(delay, &words)?;
+        self.current = Some(*rs);
+        Ok(())
+    }
+
+    /// Writes registers out in the given sequence (datasheet "Program Modes"):
+    /// after power-up all six registers go out R5 first, later updates,
+    /// e.g. a FRAC-only change, only need changed registers with R0 last.
+    /// `WriteOrder::FracUpdate` writes everything out until the driver has
+    /// written the whole set once.
+    /// Blocking call.
+    pub fn write_register_set_order<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: &RegisterSet,
+        order: WriteOrder,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        match (order, self.current) {
+            (WriteOrder::FracUpdate, Some(old)) => self.write_changed(delay, &old, rs),
+            _ => self.write_register_set(delay, rs),
+        }
+    }
+
+    /// Writes register words in the given order, each one is latched
+    /// with its own LE pulse, so each word is a separate SPI write.
+    /// Blocking call.
+    pub fn write_words<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        words: &[u32],
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        for w in words {
+            self.write_register(delay, *w)?;
         }
         Ok(())
     }
 
+    /// Registers as last written by this driver,
+    /// `None` until the whole set is written out once.
+    pub fn current(&self) -> Option<&RegisterSet> {
+        self.current.as_ref()
+    }
+
+    /// Sets FRAC-N output frequency (see `FracN::set_f_out_exact`) starting from
+    /// the last written registers, only writes out the changed ones.
+    /// Returns actual output frequency.
+    pub fn set_frequency<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        ref_in_hz: u32,
+        f_out_hz: u64,
+    ) -> Result<u64, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let old = self.current.ok_or(Error::NoRegisterState)?;
+        let fracn = FracN(Fpfd::new(ref_in_hz, &old)?);
+        let (new, f_actual_hz) = fracn.set_f_out_exact(f_out_hz, ref_in_hz, old)?;
+        self.write_changed(delay, &old, &new)?;
+        Ok(f_actual_hz)
+    }
+
+    /// Deterministic bring-up: writes all registers with the R and N counters
+    /// held in reset (`CounterReset::Enabled`), then releases the reset with
+    /// another R2 write. `rs` counter reset setting is ignored.
+    /// Blocking call.
+    pub fn initialize<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: &RegisterSet,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        self.write_register_set(delay, &rs.set(CounterReset::Enabled))?;
+        self.write_register(delay, rs.set(CounterReset::Disabled).r2.w)
+    }
+
+    /// Writes out only the registers that differ between `old` and `new`,
+    /// in R5 to R1 order, followed by R0, which is always written last.
+    /// Writing R0 applies double buffered settings and triggers the update.
+    /// Blocking call.
+    pub fn write_changed<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        old: &RegisterSet,
+        new: &RegisterSet,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let changed = new.diff(old);
+        let new = new.to_words();
+        for i in (1 .. new.len()).rev() {
+            if changed[i] {
+                self.write_register(delay, new[i])?;
+            }
+        }
+        self.write_register(delay, new[0])
+    }
+
+    /// Software power-down, sets the R2 power-down bit and writes R2.
+    /// Registers keep their contents, VCO, charge pump and RF outputs are off.
+    /// Returns updated registers.
+    pub fn power_down<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(PowerDown::Enabled);
+        self.write_register(delay, rs.r2.w)?;
+        Ok(rs)
+    }
+
+    /// Clears the R2 power-down bit and writes all registers out to re-lock.
+    /// Returns updated registers.
+    pub fn power_up<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(PowerDown::Disabled);
+        self.write_register_set(delay, &rs)?;
+        Ok(rs)
+    }
+
+    /// Parks the PLL for loop filter bring-up: charge pump three-state and
+    /// R/N counters held in reset, so the VCO doesn't rail while registers load.
+    /// Writes R2 only. Returns updated registers.
+    pub fn park<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(ChargePumpThreeState::Enabled).set(CounterReset::Enabled);
+        self.write_register(delay, rs.r2.w)?;
+        Ok(rs)
+    }
+
+    /// Releases `park`: charge pump back to normal operation, counters out of reset.
+    /// Writes R2 only. Returns updated registers.
+    pub fn unpark<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(ChargePumpThreeState::Disabled).set(CounterReset::Disabled);
+        self.write_register(delay, rs.r2.w)?;
+        Ok(rs)
+    }
+
+    /// Brings the part to a known quiescent state regardless of prior
+    /// configuration: writes all registers from `recommended_defaults` with
+    /// RF and aux outputs off, VCO powered down, charge pump three-state
+    /// and R/N counters held in reset.
+    /// Blocking call.
+    pub fn reset<Delay>(
+        &mut self,
+        delay: &mut Delay,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = RegisterSet::recommended_defaults()
+            .set(RfOutputEnable::Disabled)
+            .set(AuxOutputEnable::Disabled)
+            .set(VcoPowerDown::PoweredDown)
+            .set(ChargePumpThreeState::Enabled)
+            .set(CounterReset::Enabled);
+        self.write_register_set(delay, &rs)
+    }
+
+    /// Turns the primary RF output on or off, writes R4 only.
+    /// `RfOutputEnable` isn't double buffered, so no R0 write is needed,
+    /// which would also start VCO band selection.
+    /// Returns updated registers.
+    pub fn set_output_enabled<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+        on: bool,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(if on { RfOutputEnable::Enabled } else { RfOutputEnable::Disabled });
+        self.write_register(delay, rs.r4.w)?;
+        Ok(rs)
+    }
+
+    /// Turns the auxiliary RF output on or off, writes R4 only, see `set_output_enabled`.
+    /// Returns updated registers.
+    pub fn set_aux_output_enabled<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+        on: bool,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(if on { AuxOutputEnable::Enabled } else { AuxOutputEnable::Disabled });
+        self.write_register(delay, rs.r4.w)?;
+        Ok(rs)
+    }
+
+    /// Routes a signal to MUXOUT, writes R2 only, e.g. `Muxout::RCntOut` or
+    /// `Muxout::NDivOut` for `measure_muxout_freq`. Returns updated registers.
+    pub fn set_muxout<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: RegisterSet,
+        muxout: Muxout,
+    ) -> Result<RegisterSet, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let rs = rs.set(muxout);
+        self.write_register(delay, rs.r2.w)?;
+        Ok(rs)
+    }
+
+    /// Writes a single register, e.g. `dev.write_reg(delay, rs.reg::<R0>())`,
+    /// control bits are set from the register type.
+    pub fn write_reg<R, Delay>(
+        &mut self,
+        delay: &mut Delay,
+        reg: Reg<R>,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where R: RegisterAddress,
+          Delay: DelayNs,
+    {
+        self.write_register(delay, (reg.w & !CONTROL_BITS_MASK) | R::ADDRESS)
+    }
+
     /// Data is clocked into the 32-bit shift register
     /// on each rising edge of CLK. The data is clocked in MSB first.
     ///
@@ -61,48 +582,490 @@ where CE: OutputPin,
     /// the state of the three control bits (C3, C2, and C1) in the shift
     /// register.
     #[inline(always)]
-    pub fn write_register<Delay>(self: &mut Self, delay: &mut Delay, w: u32) -> Result<(), Error>
-    where Delay: DelayUs<u16>,
-    {
-        let data = [
-            ((w >> 24) & 0xFF ) as u8,
-            ((w >> 16) & 0xFF ) as u8,
-            ((w >>  8) & 0xFF ) as u8,
-            ( w        & 0xFF ) as u8,
-        ];
-        self.spi.write(&data).map_err(|_| Error::Spi)?;
-
-        delay.delay_us(5);
-        self.load_enable()?;
-        delay.delay_us(10);
-        self.load_disable()?;
-        delay.delay_us(5);
+    pub fn write_register<Delay>(&mut self, delay: &mut Delay, w: u32) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        self.spi.write(&register_bytes(w, self.bit_order)).map_err(DeviceError::Spi)?;
+        self.spi.flush().map_err(DeviceError::Spi)?;
+
+        for step in self.timings.latch_sequence().iter() {
+            match *step {
+                LatchStep::Delay(ns) => delay.delay_ns(ns),
+                LatchStep::Le(true) => self.load_enable()?,
+                LatchStep::Le(false) => self.load_disable()?,
+            }
+        }
+
+        if let Some(rs) = self.current {
+            let mut words = rs.to_words();
+            if let Some(cw) = words.get_mut((w & CONTROL_BITS_MASK) as usize) {
+                *cw = w;
+                self.current = Some(RegisterSet::with_words(words));
+            }
+        }
+
+        // keep track of the MUXOUT mode for lock detect reads
+        if w & CONTROL_BITS_MASK == 2 {
+            self.muxout = Muxout::from_bits_or_default((w >> Muxout::offset()) & Muxout::mask());
+        }
 
         Ok(())
     }
 
     /// Powers up the device, depending on the status of the power-down bits.
     #[inline(always)]
-    pub fn enable(self: &mut Self) -> Result<(), Error> {
-        self.pin_ce.set_high().map_err(|_| Error::Pin)
+    pub fn enable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_high().map_err(DeviceError::Pin)
     }
 
     /// Powers down the device and puts the charge pump into three-state mode.
     #[inline(always)]
-    pub fn disable(self: &mut Self) -> Result<(), Error> {
-        self.pin_ce.set_low().map_err(|_| Error::Pin)
+    pub fn disable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_low().map_err(DeviceError::Pin)
     }
 
     /// When LE goes high, the data stored in the 32-bit shift register is
     /// loaded into the register that is selected by the three control bits.
     #[inline(always)]
-    pub fn load_enable(self: &mut Self) -> Result<(), Error> {
-        self.pin_le.set_high().map_err(|_| Error::Pin)
+    pub fn load_enable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_le.set_high().map_err(DeviceError::Pin)
     }
 
     /// Disable register load from shift register
     #[inline(always)]
-    fn load_disable(self: &mut Self) -> Result<(), Error> {
-        self.pin_le.set_low().map_err(|_| Error::Pin)
+    fn load_disable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_le.set_low().map_err(DeviceError::Pin)
+    }
+}
+
+
+impl<CE, LE, SPI, MUX> Adf4351<CE, LE, SPI, MUX>
+where CE: OutputPin,
+      LE: OutputPin<Error = CE::Error>,
+      SPI: SpiBus<u8>,
+      MUX: InputPin<Error = CE::Error>,
+{
+    /// Reads PLL lock state from the MUXOUT pin,
+    /// registers have to route digital lock detect to it (`Muxout::Dlock`).
+    pub fn is_locked(&mut self) -> Result<bool, DeviceError<SPI::Error, CE::Error>> {
+        self.pin_mux.is_high().map_err(DeviceError::Pin)
+    }
+
+    /// Lock detect signal on MUXOUT, as last written to R2 by this driver.
+    pub fn lock_detect_kind(&self) -> Option<LockDetectKind> {
+        self.muxout.lock_detect_kind()
+    }
+
+    /// Reads PLL lock state from the MUXOUT pin according to the lock detect
+    /// mode last written to R2. Digital lock detect is read once, analog lock
+    /// detect has to read high several times in a row to filter out
+    /// its unlocked low pulses. Fails with `Error::NoLockDetect` if MUXOUT
+    /// isn't routed to either.
+    pub fn read_lock(&mut self) -> Result<bool, DeviceError<SPI::Error, CE::Error>> {
+        match self.lock_detect_kind() {
+            Some(LockDetectKind::Digital) => self.is_locked(),
+            Some(LockDetectKind::Analog) => {
+                for _ in 0 .. ANALOG_LOCK_SAMPLES {
+                    if !self.is_locked()? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            None => Err(Error::NoLockDetect.into()),
+        }
+    }
+
+    /// Writes all registers out and checks that the device took them.
+    ///
+    /// ADF4351 registers can't be read back over SPI, MUXOUT is the only
+    /// feedback: it's driven high (`Muxout::Dvdd`) then low (`Muxout::Dgnd`)
+    /// and read back, which catches SPI/LE wiring faults, then `rs` R2 is restored.
+    /// If `rs` routes lock detect to MUXOUT, also waits up to `timeout_us` for the lock.
+    /// Blocking call.
+    pub fn verify<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: &RegisterSet,
+        timeout_us: u32,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        self.write_register_set(delay, rs)?;
+
+        self.write_register(delay, rs.set(Muxout::Dvdd).r2.w)?;
+        let high = self.pin_mux.is_high().map_err(DeviceError::Pin)?;
+        self.write_register(delay, rs.set(Muxout::Dgnd).r2.w)?;
+        let low = self.pin_mux.is_low().map_err(DeviceError::Pin)?;
+        self.write_register(delay, rs.r2.w)?;
+
+        if !(high && low) {
+            return Err(DeviceError::VerifyFailed);
+        }
+        if self.lock_detect_kind().is_some() {
+            self.wait_for_lock(delay, timeout_us)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the MUXOUT pin level, whatever is routed to it.
+    pub fn read_muxout(&mut self) -> Result<bool, DeviceError<SPI::Error, CE::Error>> {
+        self.pin_mux.is_high().map_err(DeviceError::Pin)
+    }
+
+    /// Estimates the MUXOUT signal frequency, Hz, by sampling the pin every
+    /// `sample_ns` over `gate_us` and counting rising edges, e.g. the R counter
+    /// (`Muxout::RCntOut`) or N divider (`Muxout::NDivOut`) output, see `set_muxout`.
+    ///
+    /// Software sampling only suits low frequencies: well below half the sampling
+    /// rate, with dividers set up for self-test. Pin reads add to `sample_ns`,
+    /// so the result reads low on slow pins. Resolution is 1/`gate_us`.
+    /// Blocking call.
+    pub fn measure_muxout_freq<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        gate_us: u32,
+        sample_ns: u32,
+    ) -> Result<u32, DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let samples = gate_us as u64 * 1000 / sample_ns.max(1) as u64;
+        let mut edges : u64 = 0;
+        let mut level = self.read_muxout()?;
+        for _ in 0 .. samples {
+            delay.delay_ns(sample_ns);
+            let next = self.read_muxout()?;
+            if next && !level {
+                edges += 1;
+            }
+            level = next;
+        }
+        Ok((edges * 1_000_000 / gate_us.max(1) as u64) as u32)
+    }
+
+    /// Polls MUXOUT lock detect (see `read_lock`) until the PLL locks or `timeout_us` passes.
+    pub fn wait_for_lock<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        timeout_us: u32,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let mut waited_us = 0;
+        loop {
+            if self.read_lock()? {
+                return Ok(());
+            }
+            if waited_us >= timeout_us {
+                return Err(DeviceError::LockTimeout);
+            }
+            delay.delay_us(LOCK_POLL_US);
+            waited_us = waited_us.saturating_add(LOCK_POLL_US);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mock::*;
+
+    /// Register set from examples/freq.rs, 63 MHz out of a 25 MHz reference
+    fn example_register_set() -> RegisterSet {
+        let rs = RegisterSet::recommended_defaults()
+            .set(DoubleBuffer::Enabled)
+            .set(Mod(4000))
+            .set(ChargePumpCurrent(0b111))
+            .set(PhaseDetectorPolarity::Positive)
+            .set(R(1))
+            .set(RefDoubler::Enabled)
+            .set(Rdiv2::Enabled)
+            .set(LockDetectPin::DigitalLockDetect)
+            .set(BandSelectClockDiv(200))
+            .set(AuxOutputEnable::Enabled)
+            .set(AuxRfPower(RfPower::Plus2dBm))
+            .set(RfOutputEnable::Enabled)
+            .set(RfPower::Plus2dBm);
+        let rs = FracN::init(rs);
+        let fracn = FracN(Fpfd::new(25_000_000, &rs).unwrap());
+        fracn.set_f_out(63_000_000, rs).unwrap()
+    }
+
+    fn mock_device(log: &Log) -> Adf4351<MockPin, MockPin, MockSpi> {
+        Adf4351::new(MockSpi(log.clone()), MockPin(log.clone(), Event::Ce), MockPin(log.clone(), Event::Le))
+    }
+
+    #[test]
+    fn example_frequency_registers() {
+        let rs = example_register_set();
+
+        let int : Int = rs.get();
+        let frac : Frac = rs.get();
+        let rfdiv : RfDividerSelect = rs.get();
+        assert_eq!((int.0, frac.0, rfdiv.0), (161, 1120, 6));
+        assert_eq!(rs.f_out_hz(25_000_000).unwrap(), 63_000_000);
+    }
+
+    #[test]
+    fn write_register_set_stream() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let rs = example_register_set();
+
+        dev.write_register_set(&mut MockDelay::default(), &rs).unwrap();
+
+        // R5 to R0, 32 bits each, MSB first
+        let expected : Vec<u8> = rs.to_words().iter().rev().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(spi_bytes(&log), expected);
+
+        // each word is flushed out before an LE pulse latches it
+        let words = rs.to_words();
+        let events = log.borrow();
+        assert_eq!(events.len(), 6 * 4);
+        for (i, chunk) in events.chunks(4).enumerate() {
+            assert_eq!(chunk, &[
+                Event::Spi(words[5 - i].to_be_bytes().to_vec()),
+                Event::Flush,
+                Event::Le(true),
+                Event::Le(false),
+            ]);
+        }
+    }
+
+    #[test]
+    fn timings_reject_short_le_pulse() {
+        assert_eq!(Timings::new(0, LE_PULSE_MIN_NS - 1, 0).err(), Some(Error::InvalidTiming));
+        assert!(Timings::new(0, LE_PULSE_MIN_NS, 0).is_ok());
+
+        let log = log();
+        let short = Timings { le_pulse_ns: 0, ..DEFAULT_TIMINGS };
+        assert_eq!(mock_device(&log).with_timings(short).err(), Some(Error::InvalidTiming));
+        assert!(mock_device(&log).with_timings(Timings::new(10, 20, 10).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn register_byte_order() {
+        assert_eq!(register_bytes(0x0050_A300, BitOrder::MsbFirst), [0x00, 0x50, 0xA3, 0x00]);
+        // 0x50 = 0b0101_0000, 0xA3 = 0b1010_0011
+        assert_eq!(register_bytes(0x0050_A300, BitOrder::LsbFirst), [0x00, 0b0000_1010, 0b1100_0101, 0x00]);
+
+        let log = log();
+        let mut dev = mock_device(&log).with_bit_order(BitOrder::LsbFirst);
+        dev.write_register(&mut MockDelay::default(), 0x0000_0001).unwrap();
+        assert_eq!(spi_bytes(&log), [0x00, 0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn write_order_frac_update() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let mut delay = MockDelay::default();
+        let rs = example_register_set();
+
+        // nothing written yet, all six go out
+        dev.write_register_set_order(&mut delay, &rs, WriteOrder::FracUpdate).unwrap();
+        assert_eq!(spi_bytes(&log).len(), 6 * 4);
+
+        log.borrow_mut().clear();
+        let new = rs.set(Frac(7));
+        dev.write_register_set_order(&mut delay, &new, WriteOrder::FracUpdate).unwrap();
+        assert_eq!(spi_bytes(&log), new.r0.w.to_be_bytes());
+        assert_eq!(dev.current(), Some(&new));
+
+        log.borrow_mut().clear();
+        dev.write_register_set_order(&mut delay, &new, WriteOrder::PowerUp).unwrap();
+        assert_eq!(spi_bytes(&log).len(), 6 * 4);
+    }
+
+    #[test]
+    fn write_changed_ends_with_r0() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let old = example_register_set();
+        let new = old.set(RfPower::Minus4dBm);
+
+        dev.write_changed(&mut MockDelay::default(), &old, &new).unwrap();
+
+        let expected : Vec<u8> = [new.r4.w, new.r0.w].iter().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(spi_bytes(&log), expected);
+    }
+
+    #[test]
+    fn write_reg_single_register() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let rs = example_register_set();
+        dev.write_register_set(&mut MockDelay::default(), &rs).unwrap();
+
+        log.borrow_mut().clear();
+        let rs = rs.set(Frac(7));
+        dev.write_reg(&mut MockDelay::default(), rs.reg::<R0>()).unwrap();
+        assert_eq!(spi_bytes(&log), rs.r0.w.to_be_bytes());
+        assert_eq!(dev.current(), Some(&rs));
+    }
+
+    #[test]
+    fn park_unpark_writes_r2() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let mut delay = MockDelay::default();
+        let rs = example_register_set();
+        dev.write_register_set(&mut delay, &rs).unwrap();
+
+        log.borrow_mut().clear();
+        let parked = dev.park(&mut delay, rs).unwrap();
+        assert!(matches!(parked.get(), ChargePumpThreeState::Enabled));
+        assert!(matches!(parked.get(), CounterReset::Enabled));
+        assert_eq!(dev.current(), Some(&parked));
+
+        let unparked = dev.unpark(&mut delay, parked).unwrap();
+        assert_eq!(unparked, rs);
+        assert_eq!(dev.current(), Some(&rs));
+        let expected : Vec<u8> = [parked.r2.w, rs.r2.w].iter().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(spi_bytes(&log), expected);
+    }
+
+    #[test]
+    fn spi_device_le_as_cs() {
+        let log = log();
+        let mut dev = Adf4351::new_with_spi_device(MockSpiDevice(log.clone()), MockPin(log.clone(), Event::Ce));
+        let mut delay = MockDelay::default();
+
+        dev.write_register(&mut delay, 0x0050_A300).unwrap();
+        dev.write_register(&mut delay, 0x0000_0005).unwrap();
+
+        // CS acts as LE, setup delay inside the transaction before the rising edge
+        let setup = Event::Delay(DEFAULT_TIMINGS.le_setup_ns);
+        assert_eq!(*log.borrow(), std::vec![
+            Event::Le(false), Event::Spi(std::vec![0x00, 0x50, 0xA3, 0x00]), setup.clone(), Event::Le(true),
+            Event::Le(false), Event::Spi(std::vec![0x00, 0x00, 0x00, 0x05]), setup, Event::Le(true),
+        ]);
+        // LE pulse is still guaranteed between transactions
+        assert!(delay.total_ns >= 2 * DEFAULT_TIMINGS.le_pulse_ns as u64);
+    }
+
+    #[test]
+    fn reset_writes_quiescent_set() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let mut delay = MockDelay::default();
+        dev.write_register_set(&mut delay, &example_register_set()).unwrap();
+
+        log.borrow_mut().clear();
+        dev.reset(&mut delay).unwrap();
+        let rs = *dev.current().unwrap();
+        assert!(matches!(rs.get(), RfOutputEnable::Disabled));
+        assert!(matches!(rs.get(), AuxOutputEnable::Disabled));
+        assert!(matches!(rs.get(), VcoPowerDown::PoweredDown));
+        assert!(matches!(rs.get(), ChargePumpThreeState::Enabled));
+        assert!(matches!(rs.get(), CounterReset::Enabled));
+        assert!(rs.r5.reserved_bits_ok());
+
+        let expected : Vec<u8> = rs.to_words().iter().rev().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(spi_bytes(&log), expected);
+    }
+
+    #[test]
+    fn output_toggle_writes_r4_only() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let mut delay = MockDelay::default();
+        let rs = example_register_set();
+        dev.write_register_set(&mut delay, &rs).unwrap();
+
+        log.borrow_mut().clear();
+        let off = dev.set_output_enabled(&mut delay, rs, false).unwrap();
+        assert_eq!(spi_bytes(&log), off.r4.w.to_be_bytes());
+        assert_eq!(dev.current(), Some(&off));
+
+        let aux_on = dev.set_aux_output_enabled(&mut delay, off, true).unwrap();
+        assert!(matches!(aux_on.get(), AuxOutputEnable::Enabled));
+        assert_eq!(dev.current(), Some(&aux_on));
+    }
+
+    #[test]
+    fn set_frequency_tracks_registers() {
+        let log = log();
+        let mut dev = mock_device(&log);
+        let mut delay = MockDelay::default();
+        assert!(dev.set_frequency(&mut delay, 25_000_000, 100_000_000).is_err());
+
+        let rs = example_register_set();
+        dev.initialize(&mut delay, &rs).unwrap();
+        assert_eq!(dev.current(), Some(&rs));
+
+        log.borrow_mut().clear();
+        let f = dev.set_frequency(&mut delay, 25_000_000, 100_000_000).unwrap();
+        assert_eq!(f, 100_000_000);
+        assert_eq!(dev.current().unwrap().f_out_hz(25_000_000).unwrap(), 100_000_000);
+        // R4 (RF divider), R1 (MOD) and R0 only
+        assert_eq!(spi_bytes(&log).len(), 3 * 4);
+    }
+
+    #[test]
+    fn measure_muxout_counts_rising_edges() {
+        let log = log();
+        // 10 samples after the first one, 3 rising edges
+        let levels = std::vec![false, true, true, false, true, false, false, true, true, true, true];
+        let mut dev = mock_device(&log).with_muxout(MockInputPin(levels));
+        let mut delay = MockDelay::default();
+
+        let rs = dev.set_muxout(&mut delay, example_register_set(), Muxout::RCntOut).unwrap();
+        assert!(matches!(rs.get(), Muxout::RCntOut));
+        assert_eq!(spi_bytes(&log), rs.r2.w.to_be_bytes());
+
+        delay.total_ns = 0;
+        // 10 µs gate, 1 µs samples
+        assert_eq!(dev.measure_muxout_freq(&mut delay, 10, 1000), Ok(300_000));
+        assert_eq!(delay.total_ns, 10_000);
+    }
+
+    #[test]
+    fn verify_forces_muxout_levels() {
+        let log = log();
+        // high on DVDD, low on DGND, then locked
+        let mut dev = mock_device(&log).with_muxout(MockInputPin(std::vec![true, false, true]));
+        let mut delay = MockDelay::default();
+        let rs = example_register_set().set(Muxout::Dlock);
+
+        assert_eq!(dev.verify(&mut delay, &rs, 100), Ok(()));
+        assert_eq!(dev.current(), Some(&rs));
+        assert_eq!(dev.lock_detect_kind(), Some(LockDetectKind::Digital));
+        // full set, forced DVDD and DGND, then the original R2
+        let r2s : Vec<u8> = [rs.set(Muxout::Dvdd).r2.w, rs.set(Muxout::Dgnd).r2.w, rs.r2.w]
+            .iter().flat_map(|w| w.to_be_bytes()).collect();
+        assert!(spi_bytes(&log).ends_with(&r2s));
+    }
+
+    #[test]
+    fn verify_detects_stuck_muxout() {
+        let log = log();
+        let mut dev = mock_device(&log).with_muxout(MockInputPin(std::vec![true]));
+        let mut delay = MockDelay::default();
+        let rs = example_register_set().set(Muxout::Dlock);
+
+        assert_eq!(dev.verify(&mut delay, &rs, 100), Err(DeviceError::VerifyFailed));
+        assert_eq!(dev.current(), Some(&rs));
+        assert_eq!(dev.lock_detect_kind(), Some(LockDetectKind::Digital));
+    }
+
+    #[test]
+    fn read_lock_follows_muxout() {
+        let log = log();
+        let mut dev = mock_device(&log).with_muxout(MockInputPin(std::vec![true, true, false, true]));
+        assert!(dev.read_lock().is_err()); // MUXOUT isn't configured yet
+
+        let rs = example_register_set().set(Muxout::Alock);
+        dev.write_register_set(&mut MockDelay::default(), &rs).unwrap();
+        assert_eq!(dev.lock_detect_kind(), Some(LockDetectKind::Analog));
+        assert!(!dev.read_lock().unwrap()); // low pulse among the samples
+
+        let rs = rs.set(Muxout::Dlock);
+        dev.write_register_set(&mut MockDelay::default(), &rs).unwrap();
+        assert!(dev.read_lock().unwrap());
     }
 }
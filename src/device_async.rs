@@ -0,0 +1,141 @@
+//! Device pins, async version of `device` (`async` feature)
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{
+    delay::DelayNs,
+    spi::SpiBus,
+};
+
+
+use crate::device::{ register_bytes, BitOrder, LatchStep, Timings, };
+use crate::errors::*;
+use crate::register::*;
+
+/// ADF4351 device, async API
+pub struct Adf4351Async<CE, LE, SPI> {
+    spi: SPI,
+    pin_ce: CE,
+    pin_le: LE,
+    timings: Timings,
+    bit_order: BitOrder,
+}
+
+
+impl<CE, LE, SPI,> Adf4351Async<CE, LE, SPI,>
+where CE: OutputPin,
+      LE: OutputPin<Error = CE::Error>,
+      SPI: SpiBus<u8>,
+{
+    /// Creates the device (unconfigured, no output).
+    ///
+    /// `spi` - SPI bus (`MOSI` => `DATA`, `CLK` => `CLK`, `CPHA` = 0)
+    /// `pin_ce` - "chip enable" pin
+    /// `pin_le` - "load enable" pin
+    ///
+    pub fn new(
+        spi: SPI,
+        pin_ce: CE,
+        pin_le: LE,
+    ) -> Self {
+        Adf4351Async { spi, pin_ce, pin_le, timings: Timings::default(), bit_order: BitOrder::default(), }
+    }
+
+    /// Replaces default LE timings, e.g. for slow or isolated SPI links.
+    /// `Error::InvalidTiming` for an LE pulse below `LE_PULSE_MIN_NS`.
+    pub fn with_timings(mut self, timings: Timings) -> Result<Self, Error> {
+        timings.validate()?;
+        self.timings = timings;
+        Ok(self)
+    }
+
+    /// Sets the SPI peripheral bit order, see `Adf4351::with_bit_order`.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Writes all control registers out.
+    pub async fn write_register_set<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        rs: &RegisterSet,
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        for r in rs.to_words().iter().rev() {
+            self.write_register(delay, *r).await?;
+        }
+        Ok(())
+    }
+
+    /// Clocks the word out MSB first (see `BitOrder`) and latches it with an LE pulse,
+    /// see `Adf4351::write_register`.
+    pub async fn write_register<Delay>(&mut self, delay: &mut Delay, w: u32) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        self.spi.write(&register_bytes(w, self.bit_order)).await.map_err(DeviceError::Spi)?;
+        self.spi.flush().await.map_err(DeviceError::Spi)?;
+
+        for step in self.timings.latch_sequence().iter() {
+            match *step {
+                LatchStep::Delay(ns) => delay.delay_ns(ns).await,
+                LatchStep::Le(true) => self.pin_le.set_high().map_err(DeviceError::Pin)?,
+                LatchStep::Le(false) => self.pin_le.set_low().map_err(DeviceError::Pin)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Powers up the device, depending on the status of the power-down bits.
+    #[inline(always)]
+    pub fn enable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_high().map_err(DeviceError::Pin)
+    }
+
+    /// Powers down the device and puts the charge pump into three-state mode.
+    #[inline(always)]
+    pub fn disable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_low().map_err(DeviceError::Pin)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::device::DEFAULT_TIMINGS;
+    use crate::mock::*;
+
+    #[test]
+    fn write_register_set_stream() {
+        let log = log();
+        let mut dev = Adf4351Async::new(MockSpi(log.clone()), MockPin(log.clone(), Event::Ce), MockPin(log.clone(), Event::Le));
+        let rs = RegisterSet::recommended_defaults().set(Int(100));
+
+        block_on(dev.write_register_set(&mut MockAsyncDelay(log.clone()), &rs)).unwrap();
+
+        // R5 to R0, 32 bits each, MSB first
+        let expected : Vec<u8> = rs.to_words().iter().rev().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(spi_bytes(&log), expected);
+
+        // each word is flushed out before an LE pulse latches it, same timings as the blocking driver
+        let words = rs.to_words();
+        let events = log.borrow();
+        assert_eq!(events.len(), 6 * 7);
+        for (i, chunk) in events.chunks(7).enumerate() {
+            assert_eq!(chunk, &[
+                Event::Spi(words[5 - i].to_be_bytes().to_vec()),
+                Event::Flush,
+                Event::Delay(DEFAULT_TIMINGS.le_setup_ns),
+                Event::Le(true),
+                Event::Delay(DEFAULT_TIMINGS.le_pulse_ns),
+                Event::Le(false),
+                Event::Delay(DEFAULT_TIMINGS.le_hold_ns),
+            ]);
+        }
+    }
+}
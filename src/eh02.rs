@@ -0,0 +1,160 @@
+//! Adapters for embedded-hal 0.2 peripherals (`eh02` feature).
+//!
+//! The driver is written against embedded-hal 1.0 traits, wrap 0.2 SPI,
+//! pins and delays to use it with an older HAL:
+//!
+//! ```ignore
+//! let mut sg = Adf4351::new(Eh02Spi(spi), Eh02Pin(pin_ce), Eh02Pin(pin_le));
+//! sg.write_register_set(&mut Eh02Delay(delay), &rs)?;
+//! ```
+
+use core::fmt::Debug;
+
+use embedded_hal::{ delay, digital, spi, };
+use embedded_hal_02::{
+    blocking::{ delay::DelayUs, spi::{ Transfer, Write, }, },
+    digital::v2::{ InputPin, OutputPin, },
+};
+
+
+/// Error of a wrapped embedded-hal 0.2 peripheral
+#[derive(Debug,Copy,Clone)]
+pub struct Eh02Error<E>(pub E);
+
+impl<E: Debug> spi::Error for Eh02Error<E> {
+    fn kind(&self) -> spi::ErrorKind { spi::ErrorKind::Other }
+}
+
+impl<E: Debug> digital::Error for Eh02Error<E> {
+    fn kind(&self) -> digital::ErrorKind { digital::ErrorKind::Other }
+}
+
+
+/// embedded-hal 0.2 blocking SPI (`Write` + `Transfer`) as a 1.0 `SpiBus`
+pub struct Eh02Spi<T>(pub T);
+
+impl<T, E> spi::ErrorType for Eh02Spi<T>
+where T: Write<u8, Error = E> + Transfer<u8, Error = E>,
+      E: Debug,
+{
+    type Error = Eh02Error<E>;
+}
+
+impl<T, E> spi::SpiBus<u8> for Eh02Spi<T>
+where T: Write<u8, Error = E> + Transfer<u8, Error = E>,
+      E: Debug,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.iter_mut().for_each(|w| *w = 0);
+        self.0.transfer(words).map(|_| ()).map_err(Eh02Error)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(words).map_err(Eh02Error)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for i in 0 .. read.len().max(write.len()) {
+            let mut w = [ write.get(i).copied().unwrap_or(0) ];
+            let r = self.0.transfer(&mut w).map_err(Eh02Error)?;
+            if let Some(x) = read.get_mut(i) { *x = r[0]; }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.transfer(words).map(|_| ()).map_err(Eh02Error)
+    }
+
+    /// 0.2 blocking transfers complete before returning
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+
+/// embedded-hal 0.2 `OutputPin` as a 1.0 `OutputPin`
+pub struct Eh02Pin<T>(pub T);
+
+impl<T, E> digital::ErrorType for Eh02Pin<T>
+where T: OutputPin<Error = E>,
+      E: Debug,
+{
+    type Error = Eh02Error<E>;
+}
+
+impl<T, E> digital::OutputPin for Eh02Pin<T>
+where T: OutputPin<Error = E>,
+      E: Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low().map_err(Eh02Error)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high().map_err(Eh02Error)
+    }
+}
+
+
+/// embedded-hal 0.2 `InputPin` as a 1.0 `InputPin`
+pub struct Eh02InputPin<T>(pub T);
+
+impl<T, E> digital::ErrorType for Eh02InputPin<T>
+where T: InputPin<Error = E>,
+      E: Debug,
+{
+    type Error = Eh02Error<E>;
+}
+
+impl<T, E> digital::InputPin for Eh02InputPin<T>
+where T: InputPin<Error = E>,
+      E: Debug,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.0.is_high().map_err(Eh02Error)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.0.is_low().map_err(Eh02Error)
+    }
+}
+
+
+/// embedded-hal 0.2 `DelayUs<u32>` as a 1.0 `DelayNs`, see `Eh02DelayU16` for `DelayUs<u16>`,
+/// nanosecond delays are rounded up to microseconds.
+pub struct Eh02Delay<T>(pub T);
+
+impl<T> delay::DelayNs for Eh02Delay<T>
+where T: DelayUs<u32>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.delay_us(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us);
+    }
+}
+
+
+/// embedded-hal 0.2 `DelayUs<u16>` as a 1.0 `DelayNs`, for HALs without
+/// `DelayUs<u32>`. Delays above 65535 µs are split into chunks,
+/// nanosecond delays are rounded up to microseconds.
+pub struct Eh02DelayU16<T>(pub T);
+
+impl<T> delay::DelayNs for Eh02DelayU16<T>
+where T: DelayUs<u16>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, mut us: u32) {
+        while us > u16::MAX as u32 {
+            self.0.delay_us(u16::MAX);
+            us -= u16::MAX as u32;
+        }
+        self.0.delay_us(us as u16);
+    }
+}
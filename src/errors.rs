@@ -1,15 +1,132 @@
 //! Error definitions
 
+use core::fmt;
 
-/// Union of all errors generated by this lib
-#[derive(Debug)]
+
+/// Configuration errors, see `DeviceError` for device operations.
+///
+/// New validation variants may be added in minor releases, matches on
+/// `Error` need a wildcard arm, e.g. falling back to the `Display` message.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error {
     /// Reference frequency outside of the allowed range
     InvalidReferenceFrequency,
+    /// Reference doubler enabled with REF IN above 30 MHz
+    DoublerInputTooHigh,
+    /// R counter outside of 1 to 1023
+    InvalidRCounter,
+    /// PFD frequency is 0 or above the INT-N maximum (45 MHz with band select, 90 MHz without)
+    InvalidPfdFrequency,
+    /// PFD frequency is above 32 MHz in FRAC-N mode
+    PfdTooHighForFracN,
     /// Output frequency outside of the allowed range
     InvalidOutputFrequency,
+    /// Frequency doesn't fit in 32 bits
+    FrequencyOutOfRange,
+    /// Output frequency is not a multiple of the PFD frequency (INT-N mode)
+    InexactOutputFrequency,
+    /// INT is below the prescaler minimum (23 for 4/5, 75 for 8/9), PFD frequency is too high
+    IntTooLow,
+    /// INT doesn't fit in 16 bits, PFD frequency is too low
+    IntTooHigh,
+    /// FRAC or MOD calculation doesn't fit its register field
+    ArithmeticOverflow,
+    /// Register word's control bits don't match its register number
+    InvalidRegisterAddress,
+    /// Bit field code doesn't match any value, e.g. a reserved one
+    InvalidFieldValue,
+    /// Lock detect function and precision don't match the mode
+    LockDetectMismatch,
+    /// R5 reserved bits DB20:DB19 aren't set to 11
+    InvalidReservedBits,
+    /// Output buffer is too small
+    BufferTooSmall,
+    /// Register map text isn't six hex words
+    InvalidHex,
+    /// Frequency sweep step is zero
+    InvalidSweepStep,
+    /// Phase is outside of 0° to 360° or not representable with the modulus
+    InvalidPhase,
+    /// Fractional modulus (MOD) outside of 2 to 4095
+    InvalidModulus,
+    /// FRAC or phase value isn't less than MOD
+    InvalidFrac,
+    /// Timeout doesn't fit in the 12 bit clock divider
+    InvalidClockDivider,
+    /// Band select clock divider is 0 or above 254 in the high band select clock mode
+    InvalidBandSelectClockDiv,
+    /// Cycle slip reduction is enabled with a charge pump current above the minimum
+    CsrChargePumpNotMinimum,
+    /// Cycle slip reduction is enabled without the reference divide-by-2 (50% PFD duty cycle)
+    CsrRequiresRdiv2,
+    /// LE pulse is shorter than the datasheet minimum
+    InvalidTiming,
+    /// No register set written out yet
+    NoRegisterState,
+    /// Device index outside of the group
+    InvalidDeviceIndex,
+    /// MUXOUT isn't configured for lock detect (`Muxout::Dlock` or `Muxout::Alock`)
+    NoLockDetect,
+}
+
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::InvalidReferenceFrequency => "reference frequency out of range",
+            Error::DoublerInputTooHigh => "reference doubler input above 30 MHz",
+            Error::InvalidRCounter => "R counter out of range",
+            Error::InvalidPfdFrequency => "PFD frequency out of range",
+            Error::PfdTooHighForFracN => "PFD frequency above FRAC-N maximum",
+            Error::InvalidOutputFrequency => "output frequency out of range",
+            Error::FrequencyOutOfRange => "frequency above 32 bits",
+            Error::InexactOutputFrequency => "output frequency not a multiple of PFD frequency",
+            Error::IntTooLow => "INT below prescaler minimum",
+            Error::IntTooHigh => "INT above 65535",
+            Error::ArithmeticOverflow => "FRAC/MOD calculation overflow",
+            Error::InvalidRegisterAddress => "register control bits mismatch",
+            Error::InvalidFieldValue => "unknown bit field code",
+            Error::LockDetectMismatch => "lock detect settings don't match mode",
+            Error::InvalidReservedBits => "R5 reserved bits not set",
+            Error::BufferTooSmall => "buffer too small",
+            Error::InvalidHex => "invalid register map text",
+            Error::InvalidSweepStep => "zero sweep step",
+            Error::InvalidPhase => "phase out of range",
+            Error::InvalidModulus => "MOD out of range",
+            Error::InvalidFrac => "FRAC or phase not less than MOD",
+            Error::InvalidClockDivider => "clock divider value out of range",
+            Error::InvalidBandSelectClockDiv => "band select clock divider out of range",
+            Error::CsrChargePumpNotMinimum => "cycle slip reduction needs minimum charge pump current",
+            Error::CsrRequiresRdiv2 => "cycle slip reduction needs reference divide-by-2",
+            Error::InvalidTiming => "LE pulse too short",
+            Error::NoRegisterState => "no registers written yet",
+            Error::InvalidDeviceIndex => "device index out of range",
+            Error::NoLockDetect => "MUXOUT not configured for lock detect",
+        })
+    }
+}
+
+
+/// Errors of device operations, keeps underlying SPI and pin errors
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeviceError<SpiE, PinE> {
+    /// Configuration error
+    Config(Error),
     /// SPI communication error
-    Spi,
-    /// Pin toggle error (should never happen)
-    Pin,
+    Spi(SpiE),
+    /// Pin toggle error
+    Pin(PinE),
+    /// PLL didn't lock in time
+    LockTimeout,
+    /// MUXOUT didn't follow forced high/low levels, see `Adf4351::verify`
+    VerifyFailed,
+}
+
+impl<SpiE, PinE> From<Error> for DeviceError<SpiE, PinE> {
+    fn from(e: Error) -> Self {
+        DeviceError::Config(e)
+    }
 }
@@ -0,0 +1,165 @@
+//! Several devices sharing one SPI bus
+
+use embedded_hal:: {
+    digital::OutputPin,
+    delay::DelayNs,
+    spi::SpiBus,
+};
+
+use crate::device::{ register_bytes, BitOrder, LatchStep, Timings, };
+use crate::errors::*;
+use crate::register::*;
+
+
+/// `N` ADF4351 devices on a shared SPI bus (`DATA`, `CLK`) and chip enable,
+/// each with its own load enable (LE) pin.
+///
+/// ADF4351 has no serial data output, so devices can't be daisy-chained,
+/// each one latches its shift register on its own LE pulse.
+/// Updates are interleaved instead: the same register goes to all devices
+/// before the next one, R0 writes, which trigger retuning, come last and back-to-back.
+pub struct Adf4351Group<CE, LE, SPI, const N: usize> {
+    spi: SPI,
+    pin_ce: CE,
+    pins_le: [LE; N],
+    timings: Timings,
+    bit_order: BitOrder,
+}
+
+
+impl<CE, LE, SPI, const N: usize> Adf4351Group<CE, LE, SPI, N>
+where CE: OutputPin,
+      LE: OutputPin<Error = CE::Error>,
+      SPI: SpiBus<u8>,
+{
+    /// Creates the device group (unconfigured, no output).
+    ///
+    /// `spi` - shared SPI bus
+    /// `pin_ce` - shared "chip enable" pin
+    /// `pins_le` - "load enable" pins, one per device
+    pub fn new(
+        spi: SPI,
+        pin_ce: CE,
+        pins_le: [LE; N],
+    ) -> Self {
+        Adf4351Group { spi, pin_ce, pins_le, timings: Timings::default(), bit_order: BitOrder::default(), }
+    }
+
+    /// Replaces default LE timings, see `Adf4351::with_timings`.
+    pub fn with_timings(mut self, timings: Timings) -> Result<Self, Error> {
+        timings.validate()?;
+        self.timings = timings;
+        Ok(self)
+    }
+
+    /// Sets the SPI peripheral bit order, see `Adf4351::with_bit_order`.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Writes register sets out, `sets[i]` to device `i`,
+    /// R5 to all devices first, R0 last.
+    /// Blocking call.
+    pub fn write_all<Delay>(
+        &mut self,
+        delay: &mut Delay,
+        sets: &[RegisterSet; N],
+    ) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let mut words = [[0u32; 6]; N];
+        for (w, rs) in words.iter_mut().zip(sets.iter()) {
+            *w = rs.to_words();
+        }
+        for r in (0 .. 6).rev() {
+            for (i, w) in words.iter().enumerate() {
+                self.write_register(delay, i, w[r])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a register word to device `i`, see `Adf4351::write_register`,
+    /// `Error::InvalidDeviceIndex` for `i` outside of the group.
+    pub fn write_register<Delay>(&mut self, delay: &mut Delay, i: usize, w: u32) -> Result<(), DeviceError<SPI::Error, CE::Error>>
+    where Delay: DelayNs,
+    {
+        let pin_le = self.pins_le.get_mut(i).ok_or(Error::InvalidDeviceIndex)?;
+
+        self.spi.write(&register_bytes(w, self.bit_order)).map_err(DeviceError::Spi)?;
+        self.spi.flush().map_err(DeviceError::Spi)?;
+
+        for step in self.timings.latch_sequence().iter() {
+            match *step {
+                LatchStep::Delay(ns) => delay.delay_ns(ns),
+                LatchStep::Le(true) => pin_le.set_high().map_err(DeviceError::Pin)?,
+                LatchStep::Le(false) => pin_le.set_low().map_err(DeviceError::Pin)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Powers up all devices.
+    pub fn enable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_high().map_err(DeviceError::Pin)
+    }
+
+    /// Powers down all devices.
+    pub fn disable(&mut self) -> Result<(), DeviceError<SPI::Error, CE::Error>> {
+        self.pin_ce.set_low().map_err(DeviceError::Pin)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mock::*;
+
+    #[test]
+    fn write_all_interleaves_devices() {
+        let log = log();
+        let mut group = Adf4351Group::new(
+            MockSpi(log.clone()),
+            MockPin(log.clone(), Event::Ce),
+            [MockPin(log.clone(), |l| Event::LeAt(0, l)), MockPin(log.clone(), |l| Event::LeAt(1, l))],
+        );
+        let a = RegisterSet::recommended_defaults();
+        let b = a.set(Int(100));
+
+        group.write_all(&mut MockDelay::default(), &[a, b]).unwrap();
+
+        let expected : Vec<u8> = (0 .. 6).rev()
+            .flat_map(|r| [a.to_words()[r], b.to_words()[r]])
+            .flat_map(|w| w.to_be_bytes())
+            .collect();
+        assert_eq!(spi_bytes(&log), expected);
+
+        // each word is latched by its own device, device 0 then 1 per register
+        let latched : Vec<(usize, u32)> = log.borrow().iter()
+            .filter_map(|e| match e { Event::LeAt(i, true) => Some(*i), _ => None })
+            .zip(expected.chunks(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]])))
+            .collect();
+        let expected : Vec<(usize, u32)> = (0 .. 6).rev()
+            .flat_map(|r| [(0, a.to_words()[r]), (1, b.to_words()[r])])
+            .collect();
+        assert_eq!(latched, expected);
+    }
+
+    #[test]
+    fn write_register_checks_device_index() {
+        let log = log();
+        let mut group = Adf4351Group::new(
+            MockSpi(log.clone()),
+            MockPin(log.clone(), Event::Ce),
+            [MockPin(log.clone(), |l| Event::LeAt(0, l))],
+        );
+        assert_eq!(group.write_register(&mut MockDelay::default(), 1, 0), Err(DeviceError::Config(Error::InvalidDeviceIndex)));
+        assert!(log.borrow().is_empty());
+    }
+}
@@ -1,10 +1,37 @@
 //! [ADF4351](https://www.analog.com/en/products/adf4351.html) HAL driver.
 //! See [examples](https://github.com/andreyk0/adf4351-rs/tree/master/examples)
+//!
+//! Registers are built with the bitfield API in `register`,
+//! frequency calculations (PFD, INT/FRAC/MOD, output frequency) live in `config`,
+//! reference path (doubler, R counter, divide-by-2) settings in `refin`,
+//! RF output stage settings in `output`, frequency units in `units`.
+//!
+//! `config::registers_for` is the simplest entry point, it gives registers
+//! for an output frequency with sensible defaults:
+//!
+//! ```
+//! use adf4351::config::registers_for;
+//!
+//! let rs = registers_for(25_000_000, 1_000_000_000).unwrap();
+//! assert_eq!(rs.f_out_hz(25_000_000), Ok(1_000_000_000));
+//! // write out with `device::Adf4351::initialize`
+//! ```
 
 #![no_std]
 
 pub mod config;
 pub mod constants;
 pub mod device;
+#[cfg(feature = "async")]
+pub mod device_async;
+#[cfg(feature = "eh02")]
+pub mod eh02;
 pub mod errors;
+pub mod group;
+#[cfg(test)]
+mod mock;
+pub mod output;
+pub mod refin;
 pub mod register;
+pub mod sweep;
+pub mod units;
@@ -0,0 +1,225 @@
+//! Host side SPI, pin and delay mocks, record device I/O for tests
+
+extern crate std;
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use embedded_hal:: {
+    delay::DelayNs,
+    digital::{ self, InputPin, OutputPin, },
+    spi::{ self, Operation, SpiBus, SpiDevice, },
+};
+
+
+/// Recorded device I/O
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Event {
+    /// SPI bytes written
+    Spi(Vec<u8>),
+    /// SPI flush
+    Flush,
+    /// CE pin level
+    Ce(bool),
+    /// LE pin level
+    Le(bool),
+    /// LE pin level of a device in a group
+    LeAt(usize, bool),
+    /// Delay inside an SPI device transaction, ns
+    Delay(u32),
+}
+
+/// Shared I/O log
+pub type Log = Rc<RefCell<Vec<Event>>>;
+
+
+/// Recording SPI bus
+pub struct MockSpi(pub Log);
+
+impl spi::ErrorType for MockSpi {
+    type Error = Infallible;
+}
+
+impl SpiBus<u8> for MockSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.fill(0);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push(Event::Spi(words.to_vec()));
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        read.fill(0);
+        self.write(write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push(Event::Spi(words.to_vec()));
+        words.fill(0);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push(Event::Flush);
+        Ok(())
+    }
+}
+
+
+/// Recording SPI device, its chip select is logged as `Event::Le`
+pub struct MockSpiDevice(pub Log);
+
+impl spi::ErrorType for MockSpiDevice {
+    type Error = Infallible;
+}
+
+impl SpiDevice<u8> for MockSpiDevice {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut bus = MockSpi(self.0.clone());
+        self.0.borrow_mut().push(Event::Le(false));
+        for op in operations {
+            match op {
+                Operation::Read(words) => bus.read(words)?,
+                Operation::Write(words) => bus.write(words)?,
+                Operation::Transfer(read, write) => bus.transfer(read, write)?,
+                Operation::TransferInPlace(words) => bus.transfer_in_place(words)?,
+                Operation::DelayNs(ns) => self.0.borrow_mut().push(Event::Delay(*ns)),
+            }
+        }
+        self.0.borrow_mut().push(Event::Le(true));
+        Ok(())
+    }
+}
+
+
+/// Recording output pin, `Event::Ce` or `Event::Le` constructor tells which one it is
+pub struct MockPin(pub Log, pub fn(bool) -> Event);
+
+impl digital::ErrorType for MockPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push((self.1)(false));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push((self.1)(true));
+        Ok(())
+    }
+}
+
+
+/// Input pin reading levels from a list, repeats the last one when it runs out
+pub struct MockInputPin(pub Vec<bool>);
+
+impl digital::ErrorType for MockInputPin {
+    type Error = Infallible;
+}
+
+impl InputPin for MockInputPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let level = self.0.first().copied().unwrap_or(false);
+        if self.0.len() > 1 {
+            self.0.remove(0);
+        }
+        Ok(level)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|h| !h)
+    }
+}
+
+
+/// Delay that only adds up requested time
+#[derive(Default)]
+pub struct MockDelay {
+    /// Total requested delay, ns
+    pub total_ns: u64,
+}
+
+impl DelayNs for MockDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.total_ns += ns as u64;
+    }
+}
+
+
+/// New empty log
+pub fn log() -> Log {
+    Rc::new(RefCell::new(Vec::new()))
+}
+
+/// SPI bytes of all recorded writes, concatenated
+pub fn spi_bytes(log: &Log) -> Vec<u8> {
+    log.borrow().iter().flat_map(|e| match e {
+        Event::Spi(bytes) => bytes.clone(),
+        _ => Vec::new(),
+    }).collect()
+}
+
+
+/// Async versions of the mocks (`async` feature), complete immediately
+#[cfg(feature = "async")]
+mod asynch {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{ Context, Poll, Waker, };
+
+    use embedded_hal_async::{ delay::DelayNs, spi::SpiBus, };
+
+    use super::*;
+
+    impl SpiBus<u8> for MockSpi {
+        async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiBus::read(self, words)
+        }
+
+        async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiBus::write(self, words)
+        }
+
+        async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiBus::transfer(self, read, write)
+        }
+
+        async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiBus::transfer_in_place(self, words)
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            embedded_hal::spi::SpiBus::flush(self)
+        }
+    }
+
+    /// Async delay, recorded as `Event::Delay`
+    pub struct MockAsyncDelay(pub Log);
+
+    impl DelayNs for MockAsyncDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.0.borrow_mut().push(Event::Delay(ns));
+        }
+    }
+
+    /// Polls a future that never waits to completion
+    pub fn block_on<F: Future>(f: F) -> F::Output {
+        let mut f = pin!(f);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(out) = f.as_mut().poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use asynch::*;
@@ -0,0 +1,75 @@
+//! RF output stage (R4 output enables, power levels, aux routing, feedback)
+
+use crate::{ register::*, };
+
+
+/// RF output stage settings, grouped R4 fields.
+///
+/// `AuxOutputSelect::Fundamental` routes the VCO fundamental to the aux output,
+/// bypassing the RF divider, while `FeedbackSelect` picks the N counter input.
+/// `FracN`/`IntN` and `FrequencyPlan` take both into account: with `Fundamental`
+/// the N counter divides the VCO frequency, with `Divided` it divides the RF
+/// divider output, so INT/FRAC are computed from the output frequency instead.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OutputConfig {
+    /// Primary RF output (RFOUTA) enable
+    pub rf_enable: bool,
+    /// Primary RF output power
+    pub rf_power: RfPower,
+    /// Auxiliary RF output (RFOUTB) enable
+    pub aux_enable: bool,
+    /// Auxiliary RF output power
+    pub aux_power: RfPower,
+    /// Auxiliary output source, divided output or VCO fundamental
+    pub aux_select: AuxOutputSelect,
+    /// N counter feedback, divided output or VCO fundamental
+    pub feedback: FeedbackSelect,
+    /// Keep RF outputs off until digital lock detect
+    pub mute_till_lock: bool,
+}
+
+/// RF output on at +5 dBm, aux output off, fundamental feedback
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            rf_enable: true,
+            rf_power: RfPower::Plus5dBm,
+            aux_enable: false,
+            aux_power: RfPower::Minus4dBm,
+            aux_select: AuxOutputSelect::Divided,
+            feedback: FeedbackSelect::Fundamental,
+            mute_till_lock: false,
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Output settings currently in R4
+    pub fn from_registers(rs: &RegisterSet) -> Self {
+        let rf_enable : RfOutputEnable = rs.get();
+        let aux_enable : AuxOutputEnable = rs.get();
+        let aux_power : AuxRfPower = rs.get();
+        let mute_till_lock : MuteTillLockDetect = rs.get();
+        OutputConfig {
+            rf_enable: matches!(rf_enable, RfOutputEnable::Enabled),
+            rf_power: rs.get(),
+            aux_enable: matches!(aux_enable, AuxOutputEnable::Enabled),
+            aux_power: aux_power.0,
+            aux_select: rs.get(),
+            feedback: rs.get(),
+            mute_till_lock: matches!(mute_till_lock, MuteTillLockDetect::Enabled),
+        }
+    }
+
+    /// Writes output settings to R4
+    pub fn apply(&self, rs: RegisterSet) -> RegisterSet {
+        rs.set(if self.rf_enable { RfOutputEnable::Enabled } else { RfOutputEnable::Disabled })
+          .set(self.rf_power)
+          .set(if self.aux_enable { AuxOutputEnable::Enabled } else { AuxOutputEnable::Disabled })
+          .set(AuxRfPower(self.aux_power))
+          .set(self.aux_select)
+          .set(self.feedback)
+          .set(if self.mute_till_lock { MuteTillLockDetect::Enabled } else { MuteTillLockDetect::Disabled })
+    }
+}
@@ -0,0 +1,181 @@
+//! Reference input path (REF IN doubler, R counter, divide-by-2)
+
+use core::convert::TryFrom;
+
+use crate::{ config::SynthMode, constants::*, errors::*, register::*, units::Hertz, };
+
+
+/// Reference input path settings.
+/// f PFD = REF IN × [(1 + D)/(R × (1 + T))]
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RefIn {
+    /// REF IN frequency, Hz
+    pub f_hz: u32,
+    /// D, reference doubler
+    pub doubler: bool,
+    /// R, reference counter (1 to 1023)
+    pub r: u16,
+    /// T, reference divide-by-2
+    pub rdiv2: bool,
+    /// DC-coupled CMOS square wave REF IN, allowed below 10 MHz
+    pub square_wave: bool,
+}
+
+impl RefIn {
+    /// Checks REF IN frequency (10 to 250 MHz, inclusive) and R counter ranges,
+    /// REF IN can't be above 30 MHz with the doubler enabled.
+    pub fn new(
+        f_hz: u32,
+        doubler: bool,
+        r: u16,
+        rdiv2: bool,
+    ) -> Result<Self, Error> {
+        let refin = RefIn { f_hz, doubler, r, rdiv2, square_wave: false, };
+        refin.validate().map(|_| refin)
+    }
+
+    /// Like `new`, with REF IN in `Hertz`.
+    pub fn new_hertz(
+        f: Hertz,
+        doubler: bool,
+        r: u16,
+        rdiv2: bool,
+    ) -> Result<Self, Error> {
+        let f_hz = u32::try_from(f).map_err(|_| Error::InvalidReferenceFrequency)?;
+        RefIn::new(f_hz, doubler, r, rdiv2)
+    }
+
+    /// Like `new`, for REF IN below 10 MHz. The datasheet allows it for
+    /// a DC-coupled, CMOS-compatible square wave with a slew rate above 21 V/µs,
+    /// without a lower limit, so only 0 Hz is rejected.
+    /// Checks that take a plain REF IN frequency assume a sine wave, use
+    /// `Fpfd::with_ref_in`, `RegisterSet::validate_ref_in` and `RegisterSet::f_out_hz_ref_in`.
+    pub fn new_square_wave(
+        f_hz: u32,
+        doubler: bool,
+        r: u16,
+        rdiv2: bool,
+    ) -> Result<Self, Error> {
+        let refin = RefIn { f_hz, doubler, r, rdiv2, square_wave: true, };
+        refin.validate().map(|_| refin)
+    }
+
+    /// Checks REF IN frequency, doubler and R counter limits, see `new`, `new_square_wave`.
+    pub fn validate(&self) -> Result<(), Error> {
+        let f_min = if self.square_wave { 1 } else { REF_IN_FREQ_MIN };
+        if !(f_min ..= REF_IN_FREQ_MAX).contains(&self.f_hz) {
+            return Err(Error::InvalidReferenceFrequency);
+        }
+        if self.doubler && self.f_hz > REF_IN_DOUBLER_FREQ_MAX {
+            return Err(Error::DoublerInputTooHigh);
+        }
+        if !(R_MIN ..= R_MAX).contains(&self.r) {
+            return Err(Error::InvalidRCounter);
+        }
+        Ok(())
+    }
+
+    /// Reference path settings currently in R2, REF IN frequency isn't checked
+    /// and is assumed to be a sine wave.
+    pub fn from_registers(f_hz: u32, rs: &RegisterSet) -> Self {
+        let doubler : RefDoubler = rs.get();
+        let r : R = rs.get();
+        let rdiv2 : Rdiv2 = rs.get();
+        RefIn {
+            f_hz,
+            doubler: matches!(doubler, RefDoubler::Enabled),
+            r: r.0,
+            rdiv2: matches!(rdiv2, Rdiv2::Enabled),
+            square_wave: false,
+        }
+    }
+
+    /// Settings that get closest to the target PFD frequency, see `optimal_for_mode`,
+    /// limited to the INT-N maximum (90 MHz).
+    pub fn optimal(f_hz: u32, target_pfd_hz: u32) -> Result<Self, Error> {
+        RefIn::optimal_for_mode(f_hz, target_pfd_hz, SynthMode::IntegerN)
+    }
+
+    /// Settings that get closest to the target PFD frequency without going
+    /// above it or the mode maximum: 32 MHz in FRAC-N, 90 MHz in INT-N
+    /// (45 MHz applies with VCO band select, see `Fpfd::new`).
+    /// R is rounded up, settings that still end up above the limit are skipped.
+    /// The doubler is only considered up to 30 MHz REF IN,
+    /// on ties settings without the doubler and divide-by-2 are preferred.
+    pub fn optimal_for_mode(f_hz: u32, target_pfd_hz: u32, mode: SynthMode) -> Result<Self, Error> {
+        if !(REF_IN_FREQ_MIN ..= REF_IN_FREQ_MAX).contains(&f_hz) {
+            return Err(Error::InvalidReferenceFrequency);
+        }
+        if target_pfd_hz == 0 || target_pfd_hz > PFD_FREQ_INTN_MAX {
+            return Err(Error::InvalidPfdFrequency);
+        }
+        let max_pfd_hz = target_pfd_hz.min(match mode {
+            SynthMode::FractionalN => PFD_FREQ_FRACN_MAX,
+            SynthMode::IntegerN => PFD_FREQ_INTN_MAX,
+        });
+
+        let mut best : Option<(RefIn, u32)> = None;
+        for &doubler in [false, true].iter() {
+            if doubler && f_hz > REF_IN_DOUBLER_FREQ_MAX {
+                continue;
+            }
+            for &rdiv2 in [false, true].iter() {
+                // f PFD × R = REF IN × (1 + D) / (1 + T), rounded up
+                let f_scaled = f_hz as u64 * (1 + doubler as u64) / (1 + rdiv2 as u64);
+                let r = f_scaled.div_ceil(max_pfd_hz as u64);
+                let r = r.clamp(R_MIN as u64, R_MAX as u64) as u16;
+
+                let refin = RefIn { f_hz, doubler, r, rdiv2, square_wave: false, };
+                if refin.f_pfd_hz() > max_pfd_hz {
+                    continue;
+                }
+                let err = refin.f_pfd_hz().abs_diff(target_pfd_hz);
+                let better = match best {
+                    Some((_, best_err)) => err < best_err,
+                    None => true,
+                };
+                if better {
+                    best = Some((refin, err));
+                }
+            }
+        }
+
+        best.map(|(refin, _)| refin).ok_or(Error::InvalidPfdFrequency)
+    }
+
+    /// Phase Frequency Detector' frequency, Hz
+    pub fn f_pfd_hz(&self) -> u32 {
+        (self.f_hz as u64 * (1 + self.doubler as u64)
+            / (self.r.max(R_MIN) as u64 * (1 + self.rdiv2 as u64))) as u32
+    }
+
+    /// Writes reference path settings to R2.
+    pub fn apply(&self, rs: RegisterSet) -> RegisterSet {
+        rs.set(if self.doubler { RefDoubler::Enabled } else { RefDoubler::Disabled })
+          .set(R(self.r))
+          .set(if self.rdiv2 { Rdiv2::Enabled } else { Rdiv2::Disabled })
+    }
+}
+
+
+/// Reference path in the `.set()` chain
+impl RegisterSet {
+    /// Sets R counter, doubler and divide-by-2 from the validated reference path,
+    /// see `RefIn::apply`.
+    pub fn with_reference(self, refin: &RefIn) -> Self {
+        refin.apply(self)
+    }
+
+    /// Sets R counter, doubler and divide-by-2 for the PFD frequency closest
+    /// to the target without going above it (see `RefIn::optimal_for_mode`),
+    /// REF IN and target PFD frequency have to be within spec,
+    /// the target within the FRAC-N limit (32 MHz).
+    pub fn set_reference(self, ref_in_hz: u32, target_pfd_hz: u32) -> Result<Self, Error> {
+        if target_pfd_hz > PFD_FREQ_FRACN_MAX {
+            return Err(Error::PfdTooHighForFracN);
+        }
+        let refin = RefIn::optimal_for_mode(ref_in_hz, target_pfd_hz, SynthMode::FractionalN)?;
+        Ok(self.with_reference(&refin))
+    }
+}
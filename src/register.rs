@@ -1,15 +1,45 @@
 //! ADF4351 registers
 
+use core::convert::TryFrom;
+use core::fmt;
 use core::marker::PhantomData;
 
+use crate::constants::*;
+use crate::errors::*;
+
+/// Register number of a marker type
+pub trait RegisterAddress {
+    /// Register address, control bits (C3:C1) value
+    const ADDRESS: u32;
+}
+
 /// Register number marker types
 macro_rules! gen_register_marker {
     ($r:ident, $n:tt) => {
+        gen_register_marker!($r, $n, 0);
+    };
+    ($r:ident, $n:tt, $reserved:expr) => {
         /// Register $r maker
         #[derive(Debug,Copy,Clone)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
         pub struct $r {}
 
-        impl Default for Reg<$r> { #[inline] fn default() -> Self { Reg { w: $n, phantom: PhantomData::default() } } }
+        impl RegisterAddress for $r { const ADDRESS: u32 = $n; }
+
+        /// Control bits and reserved bits that must be set
+        impl Default for Reg<$r> { #[inline] fn default() -> Self { Reg { w: $n | $reserved, phantom: PhantomData::default() } } }
+
+        impl Reg<$r> {
+            /// Register address, control bits (C3:C1) value
+            #[inline] pub const fn address() -> u32 { $n }
+
+            /// Register from a raw word, e.g. captured from hardware,
+            /// control bits are forced to the register address.
+            #[inline]
+            pub const fn from_raw(w: u32) -> Self {
+                Reg { w: (w & !CONTROL_BITS_MASK) | $n, phantom: PhantomData }
+            }
+        }
     }
 }
 
@@ -18,34 +48,62 @@ gen_register_marker!(R1, 1);
 gen_register_marker!(R2, 2);
 gen_register_marker!(R3, 3);
 gen_register_marker!(R4, 4);
-gen_register_marker!(R5, 5);
+gen_register_marker!(R5, 5, R5_RESERVED_BITS);
+
+impl Reg<R5> {
+    /// Reserved bits DB20:DB19 carry the required 11 pattern
+    #[inline]
+    pub const fn reserved_bits_ok(&self) -> bool {
+        self.w & R5_RESERVED_BITS == R5_RESERVED_BITS
+    }
+}
 
 
 /// Single config register
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Reg<R> {
     /// Config register word
     pub w: u32,
     phantom: PhantomData<R>,
 }
 
+/// Compares register words, regardless of the marker type's traits
+impl<R> PartialEq for Reg<R> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool { self.w == other.w }
+}
+
+impl<R> Eq for Reg<R> {}
+
 /// Bit operations on 32bit words
 impl<R> Reg<R> {
+    /// Register word, same as `w`
+    #[inline]
+    pub const fn raw(&self) -> u32 { self.w }
+
     #[inline]
     pub fn get<F>(self: &Self) -> F
-    where F: Sized + BitField<R> + From<u32>
+    where F: Sized + BitField<R>
     {
-        F::from(
+        F::from_bits(
             (self.w >> F::offset()) & F::mask()
         )
     }
 
     #[inline]
-    pub fn set<F>(mut self: Self, f: F) -> Self
+    pub fn set<F>(self: Self, f: F) -> Self
     where F: Sized + BitField<R> + Into<u32>
     {
-        let fbits = (f.into() & F::mask()) << F::offset();
-        let rbits = self.w & (! ( F::mask() << F::offset() ));
+        self.with_bits(F::offset(), F::mask(), f.into())
+    }
+
+    /// Const version of `set`, takes field's `OFFSET`, `MASK` and `bits()`, e.g.
+    /// `r0.with_bits(Int::OFFSET, Int::MASK, Int(161).bits())`
+    #[inline]
+    pub const fn with_bits(mut self, offset: u8, mask: u32, bits: u32) -> Self {
+        let fbits = (bits & mask) << offset;
+        let rbits = self.w & (! ( mask << offset ));
         self.w = rbits | fbits;
         self
     }
@@ -54,12 +112,15 @@ impl<R> Reg<R> {
 
 
 /// Full set of config registers.
-/// Defaults to all config bits set to 0.
+/// Defaults to all config bits set to 0, except R5 reserved bits DB20:DB19,
+/// which the datasheet requires set, see `recommended_defaults`
+/// for working functional defaults.
 ///
 /// When power is first applied to the ADF4351, the part requires
 /// six writes (one each to R5, R4, R3, R2, R1, and R0) for the output
 /// to become active.
-#[derive(Debug,Copy,Clone,Default)]
+#[derive(Debug,Copy,Clone,Default,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct RegisterSet {
     pub r0: Reg<R0>,
     pub r1: Reg<R1>,
@@ -99,19 +160,142 @@ gen_register_index!(R5, r5);
 
 impl RegisterSet {
 
-    /// Register values in device format.
+    /// Register set with reserved bits at their datasheet values
+    /// and functional fields at sane defaults.
+    ///
+    /// Bits that differ from `default()` (all zeros but R5 reserved bits):
+    /// * R5 LD pin = digital lock detect.
+    /// * R4 feedback = fundamental, RF output enabled.
+    /// * R2 R counter = 1 (0 isn't a valid divider), charge pump current 2.5 mA,
+    ///   positive PD polarity (passive loop filter).
+    /// * R1 MOD = 2 (minimum), phase = 1 (datasheet recommended value).
+    /// * R0 INT = 23 (minimum with the 4/5 prescaler).
+    ///
+    /// All other reserved bits are specified as 0.
+    pub fn recommended_defaults() -> Self {
+        RegisterSet::default()
+            .set(Int(INT_P45_MIN))
+            .set(Phase(1))
+            .set(Mod(MOD_MIN))
+            .set(R(1))
+            .set(ChargePumpCurrent(0b0111))
+            .set(PhaseDetectorPolarity::Positive)
+            .set(FeedbackSelect::Fundamental)
+            .set(RfOutputEnable::Enabled)
+            .set(LockDetectPin::DigitalLockDetect)
+    }
+
+    /// Register set from words in device format, R0 first, as is.
+    /// Const, so that a fixed configuration can live in a `static`, e.g.
+    ///
+    /// ```
+    /// use adf4351::register::*;
+    ///
+    /// static RS: RegisterSet = {
+    ///     let mut rs = RegisterSet::with_words([0, 1, 2, 3, 4, 5]);
+    ///     rs.r0 = rs.r0.with_bits(Int::OFFSET, Int::MASK, Int(161).bits());
+    ///     rs.r4 = rs.r4.with_bits(RfOutputEnable::OFFSET, RfOutputEnable::MASK, RfOutputEnable::Enabled.bits());
+    ///     rs
+    /// };
+    /// ```
+    ///
+    /// Control bits aren't checked, see `from_words`.
+    #[inline]
+    pub const fn with_words(words: [u32; 6]) -> Self {
+        RegisterSet {
+            r0: Reg { w: words[0], phantom: PhantomData },
+            r1: Reg { w: words[1], phantom: PhantomData },
+            r2: Reg { w: words[2], phantom: PhantomData },
+            r3: Reg { w: words[3], phantom: PhantomData },
+            r4: Reg { w: words[4], phantom: PhantomData },
+            r5: Reg { w: words[5], phantom: PhantomData },
+        }
+    }
+
+    /// Register values in device format, R0 first.
+    /// Debug builds check that control bits match register addresses,
+    /// which catches bitfields overlapping them.
+    #[inline]
+    pub fn to_words(&self) -> [u32; 6] {
+        let words = [ self.r0.w, self.r1.w, self.r2.w, self.r3.w, self.r4.w, self.r5.w, ];
+        debug_assert!(
+            words.iter().enumerate().all(|(i, w)| w & CONTROL_BITS_MASK == i as u32),
+            "register control bits don't match register addresses"
+        );
+        words
+    }
+
+    /// Register set from words in device format, R0 first,
+    /// e.g. captured with a logic analyzer or exported by ADI tools.
+    /// Control bits (C3:C1) of each word have to match its register number.
+    pub fn from_words(words: &[u32; 6]) -> Result<Self, Error> {
+        for (i, w) in words.iter().enumerate() {
+            if w & CONTROL_BITS_MASK != i as u32 {
+                return Err(Error::InvalidRegisterAddress);
+            }
+        }
+        Ok(RegisterSet::with_words(*words))
+    }
+
+    /// Writes registers as text, one `0x%08X` line per word, R0 to R5,
+    /// the register map format of ADI's evaluation software.
+    /// Returns number of bytes written, `ADI_HEX_LEN`.
+    pub fn to_adi_hex(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        let buf = buf.get_mut(.. ADI_HEX_LEN).ok_or(Error::BufferTooSmall)?;
+        for (line, w) in buf.chunks_mut(ADI_HEX_LEN / 6).zip(self.to_words().iter()) {
+            line[0] = b'0';
+            line[1] = b'x';
+            for (i, c) in line[2 .. 10].iter_mut().enumerate() {
+                *c = HEX[((w >> (28 - 4 * i)) & 0xF) as usize];
+            }
+            line[10] = b'\n';
+        }
+        Ok(ADI_HEX_LEN)
+    }
+
+    /// Parses registers written by `to_adi_hex` or ADI's evaluation software,
+    /// six hex words (`0x` prefix optional), R0 to R5, one per line, blank lines are skipped.
+    pub fn from_adi_hex(s: &str) -> Result<Self, Error> {
+        let mut words = [0u32; 6];
+        let mut n = 0;
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let hex = line.strip_prefix("0x").or_else(|| line.strip_prefix("0X")).unwrap_or(line);
+            let w = u32::from_str_radix(hex, 16).map_err(|_| Error::InvalidHex)?;
+            *words.get_mut(n).ok_or(Error::InvalidHex)? = w;
+            n += 1;
+        }
+        if n != words.len() {
+            return Err(Error::InvalidHex);
+        }
+        RegisterSet::from_words(&words)
+    }
+
+    /// Registers that differ from `other`, R0 first.
+    pub fn diff(&self, other: &RegisterSet) -> [bool; 6] {
+        let (a, b) = (self.to_words(), other.to_words());
+        let mut d = [false; 6];
+        for i in 0 .. d.len() {
+            d[i] = a[i] != b[i];
+        }
+        d
+    }
+
+    /// Single register by its marker type, e.g. `rs.reg::<R0>()`
     #[inline]
-    pub fn to_words(self: &Self) -> &[u32; 6] {
-        unsafe{ core::mem::transmute::<&RegisterSet, &[u32;6]>(&self) }
+    pub fn reg<R>(&self) -> Reg<R>
+    where Self: RIdx<R>
+    {
+        self.r()
     }
 
     /// Get register bitfield value
     #[inline]
     pub fn get<F,R>(self: &Self) -> F
-    where F: Sized + BitField<R> + From<u32>,
+    where F: Sized + BitField<R>,
           Self: RIdx<R>
     {
-        F::from(
+        F::from_bits(
             (self.r().w >> F::offset()) & F::mask()
         )
     }
@@ -124,6 +308,79 @@ impl RegisterSet {
     {
         self.update_r(|r| r.set(f))
     }
+
+    /// Set output phase in degrees (0 to 359), resolution is 360°/MOD
+    /// of the currently programmed MOD. A nonzero phase also enables
+    /// phase adjustment (`Ph1PhaseAdj::On`), so that R0 updates don't resync the phase.
+    pub fn set_phase_degrees(self, deg: u16) -> Result<Self, Error> {
+        let modulus : Mod = self.get();
+        let phase = Phase::from_degrees(deg, modulus.0)?;
+        let rs = self.set(phase);
+        Ok(if phase.0 == 0 { rs } else { rs.set(Ph1PhaseAdj::On) })
+    }
+}
+
+
+
+/// Decoded register fields, one line, e.g.
+/// `INT=161 FRAC=7 MOD=25 ... RFDiv=/16 ... CP=2.50mA ... Pwr=+2dBm LD=DigitalLockDetect`
+impl fmt::Display for RegisterSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let int : Int = self.get();
+        let frac : Frac = self.get();
+        write!(f, "INT={} FRAC={} ", int.0, frac.0)?;
+
+        let phadj : Ph1PhaseAdj = self.get();
+        let prescaler : Pr1Prescaler = self.get();
+        let phase : Phase = self.get();
+        let modulus : Mod = self.get();
+        write!(f, "PhAdj={:?} Prescaler={:?} Phase={} MOD={} ", phadj, prescaler, phase.0, modulus.0)?;
+
+        let noise : NoiseMode = self.get();
+        let muxout : Muxout = self.get();
+        let doubler : RefDoubler = self.get();
+        let rdiv2 : Rdiv2 = self.get();
+        let r : R = self.get();
+        let dbuf : DoubleBuffer = self.get();
+        let cp : ChargePumpCurrent = self.get();
+        let ldf : Ldf = self.get();
+        let ldp : Ldp = self.get();
+        let pdp : PhaseDetectorPolarity = self.get();
+        let pd : PowerDown = self.get();
+        let cp3 : ChargePumpThreeState = self.get();
+        let crst : CounterReset = self.get();
+        write!(f, "Noise={:?} Muxout={:?} RefDoubler={:?} Rdiv2={:?} R={} DoubleBuffer={:?} ",
+               noise, muxout, doubler, rdiv2, r.0, dbuf)?;
+        write!(f, "CP={}.{:02}mA LDF={:?} LDP={:?} PDPolarity={:?} PowerDown={:?} CP3State={:?} CounterReset={:?} ",
+               cp.ua() / 1000, cp.ua() % 1000 / 10, ldf, ldp, pdp, pd, cp3, crst)?;
+
+        let bscm : BandSelectClockMode = self.get();
+        let abp : AntiBacklashPulseWidth = self.get();
+        let cc : ChargeCancellation = self.get();
+        let csr : CycleSlipReduction = self.get();
+        let cdm : ClockDividerMode = self.get();
+        let cdv : ClockDividerValue = self.get();
+        write!(f, "BandSelClkMode={:?} ABP={:?} ChargeCancel={:?} CSR={:?} ClkDivMode={:?} ClkDiv={} ",
+               bscm, abp, cc, csr, cdm, cdv.0)?;
+
+        let fb : FeedbackSelect = self.get();
+        let rfdiv : RfDividerSelect = self.get();
+        let bscd : BandSelectClockDiv = self.get();
+        let vcopd : VcoPowerDown = self.get();
+        let mtld : MuteTillLockDetect = self.get();
+        let auxsel : AuxOutputSelect = self.get();
+        let auxen : AuxOutputEnable = self.get();
+        let auxpwr : AuxRfPower = self.get();
+        let rfen : RfOutputEnable = self.get();
+        let pwr : RfPower = self.get();
+        write!(f, "Feedback={:?} RFDiv=/{} BandSelClkDiv={} VCO={:?} MTLD={:?} ",
+               fb, 1u8 << rfdiv.0, bscd.0, vcopd, mtld)?;
+        write!(f, "AuxSel={:?} AuxEn={:?} AuxPwr={:+}dBm RFEn={:?} Pwr={:+}dBm ",
+               auxsel, auxen, auxpwr.0.dbm(), rfen, pwr.dbm())?;
+
+        let ld : LockDetectPin = self.get();
+        write!(f, "LD={:?}", ld)
+    }
 }
 
 
@@ -140,14 +397,28 @@ pub trait BitField<R> {
     fn mask() -> u32 {
         !(0xFFFFFFFFu32 << Self::num_bits())
     }
+
+    /// Field value from its bits (shifted down, masked), used by `get`
+    fn from_bits(bits: u32) -> Self where Self: Sized;
 }
 
-/// Generate BitField implementation
+/// Generate BitField implementation, `$decode` maps field bits to the value
 macro_rules! gen_bitfield_impl {
-	($r:ty, $n:ident, $nb:tt, $off:tt) => {
+	($r:ty, $n:ident, $nb:tt, $off:tt, $decode:expr) => {
+        impl $n {
+            /// Number of bits in the bit field
+            pub const NUM_BITS: u8 = $nb;
+            /// Offset from 0
+            pub const OFFSET: u8 = $off;
+            /// Bit field mask, before the offset shift
+            pub const MASK: u32 = !(0xFFFFFFFFu32 << $nb);
+        }
+
         impl BitField<$r> for $n {
-            #[inline] fn num_bits() -> u8 { $nb }
-            #[inline] fn offset() -> u8 { $off }
+            #[inline] fn num_bits() -> u8 { $n::NUM_BITS }
+            #[inline] fn offset() -> u8 { $n::OFFSET }
+            #[inline] fn mask() -> u32 { $n::MASK }
+            #[inline] fn from_bits(bits: u32) -> Self { $decode(bits) }
         }
     }
 }
@@ -157,21 +428,63 @@ macro_rules! gen_bitfield_struct {
 	($(#[$meta:meta])*, $r:ty, $n:ident, $v:ty, $nb:tt, $off:tt) => {
         $(#[$meta])*
         #[derive(Debug,Copy,Clone)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $n(pub $v);
 
-        gen_bitfield_impl!($r, $n, $nb, $off);
+        gen_bitfield_impl!($r, $n, $nb, $off, $n::from);
 
-        impl From<u32> for $n { #[inline] fn from(x: u32) -> Self { $n(x as $v) } }
-        impl Into<u32> for $n { #[inline] fn into(self) -> u32 { self.0 as u32 } }
+        /// Bits above the field width are dropped
+        impl From<u32> for $n { #[inline] fn from(x: u32) -> Self { $n((x & $n::MASK) as $v) } }
+        impl From<$n> for u32 { #[inline] fn from(x: $n) -> u32 { x.0 as u32 } }
+
+        impl $n {
+            /// Field value, const version of `u32::from`
+            #[inline] pub const fn bits(self) -> u32 { self.0 as u32 }
+        }
 	};
 }
 
+/// Enum bitfields boilerplate.
+/// Decodes by matching listed variants' codes: `TryFrom<u32>` rejects unknown
+/// (e.g. reserved) codes with `Error::InvalidFieldValue`, `from_bits_or_default`
+/// and `get` map them to the first variant, no code produces an invalid value.
 macro_rules! gen_bitfield_enum {
-	($r:ty, $n:ident, $nb:tt, $off:tt) => {
-        gen_bitfield_impl!($r, $n, $nb, $off);
+	($r:ty, $n:ident, $nb:tt, $off:tt, [$v0:ident $(, $v:ident)*]) => {
+        gen_bitfield_impl!($r, $n, $nb, $off, $n::from_bits_or_default);
+
+        impl TryFrom<u32> for $n {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(x: u32) -> Result<Self, Error> {
+                if x == $n::$v0 as u32 { return Ok($n::$v0); }
+                $( if x == $n::$v as u32 { return Ok($n::$v); } )*
+                Err(Error::InvalidFieldValue)
+            }
+        }
+        impl From<$n> for u32 { #[inline] fn from(x: $n) -> u32 { x as u32 } }
+
+        impl $n {
+            /// Decodes the code, unknown codes map to the first variant
+            #[inline]
+            pub fn from_bits_or_default(x: u32) -> Self {
+                $n::try_from(x).unwrap_or($n::$v0)
+            }
+
+            /// Field value, const version of `u32::from`
+            #[inline] pub const fn bits(self) -> u32 { self as u32 }
 
-        impl From<u32> for $n { #[inline] fn from(x: u32) -> Self { x.into() } }
-        impl Into<u32> for $n { #[inline] fn into(self) -> u32 { self as u32 } }
+            /// Decodes an on-chip field code, `None` for reserved codes
+            /// or codes wider than the field.
+            #[inline]
+            pub fn from_code(code: u8) -> Option<Self> {
+                $n::try_from(u32::from(code)).ok()
+            }
+
+            /// On-chip field code
+            #[inline] pub const fn code(self) -> u8 { self as u8 }
+        }
     }
 }
 
@@ -198,6 +511,17 @@ gen_bitfield_struct!(
     , R0, Frac, u16, 12, 3
 );
 
+impl Frac {
+    /// Fractional value, 0 to (MOD − 1), FRAC = MOD is undefined in hardware.
+    pub fn checked(frac: u16, modulus: u16) -> Result<Frac, Error> {
+        if frac < modulus && frac <= Frac::MASK as u16 {
+            Ok(Frac(frac))
+        } else {
+            Err(Error::InvalidFrac)
+        }
+    }
+}
+
 
 /// The phase adjust bit (Bit DB28) enables adjustment of the output
 /// phase of a given output frequency. When phase adjustment is
@@ -210,11 +534,13 @@ gen_bitfield_struct!(
 /// for fixed frequency applications or for frequency deviations of
 /// <1 MHz from the originally selected frequency.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ph1PhaseAdj {
     Off,
     On,
 }
-gen_bitfield_enum!(R1, Ph1PhaseAdj, 1, 28);
+gen_bitfield_enum!(R1, Ph1PhaseAdj, 1, 28, [Off, On]);
 
 
 
@@ -230,13 +556,15 @@ gen_bitfield_enum!(R1, Ph1PhaseAdj, 1, 28);
 /// when operating the ADF4351 above 3.6 GHz, the prescaler must
 /// be set to 8/9.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pr1Prescaler {
     /// Prescaler = 4/5: INT N MIN = 23
     Pr45,
     /// Prescaler = 8/9: INT N MIN = 75
     Pr89,
 }
-gen_bitfield_enum!(R1, Pr1Prescaler, 1, 27);
+gen_bitfield_enum!(R1, Pr1Prescaler, 1, 27, [Pr45, Pr89]);
 
 
 gen_bitfield_struct!(
@@ -248,6 +576,28 @@ gen_bitfield_struct!(
     , R1, Phase, u16, 12, 15
 );
 
+impl Phase {
+    /// Phase word closest to the given phase in degrees (0 to 359),
+    /// for the given MOD. Phases that round up to 360° wrap around to 0.
+    pub fn from_degrees(deg: u16, modulus: u16) -> Result<Phase, Error> {
+        if deg >= 360 || modulus == 0 {
+            return Err(Error::InvalidPhase);
+        }
+        let m = modulus as u32;
+        let p = (deg as u32 * m + 180) / 360;
+        Ok(Phase((p % m) as u16))
+    }
+
+    /// Phase in degrees (0 to 359), rounded to the nearest degree, for the given MOD.
+    pub fn to_degrees(self, modulus: u16) -> u16 {
+        if modulus == 0 {
+            return 0;
+        }
+        let m = modulus as u32;
+        (((self.0 as u32 * 360 + m / 2) / m) % 360) as u16
+    }
+}
+
 
 gen_bitfield_struct!(
     /// 12-Bit Modulus Value (MOD)
@@ -258,6 +608,18 @@ gen_bitfield_struct!(
     , R1, Mod, u16, 12, 3
 );
 
+impl Mod {
+    /// Fractional modulus, 2 to 4095.
+    /// Use the tuple constructor for raw values, e.g. MOD = 1 in INT-N mode.
+    pub fn new(modulus: u16) -> Result<Mod, Error> {
+        if (MOD_MIN ..= MOD_MAX).contains(&modulus) {
+            Ok(Mod(modulus))
+        } else {
+            Err(Error::InvalidModulus)
+        }
+    }
+}
+
 
 
 
@@ -282,18 +644,26 @@ gen_bitfield_struct!(
 /// synthesizer ensures extremely low noise, and the filter attenuates
 /// the spurs. Figure 10 through Figure 12 show the trade-offs in a
 /// typical W-CDMA setup for different noise and spur settings.
+///
+/// Encoding: 00 low noise, 11 low spur, 01 and 10 are reserved.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NoiseMode {
     LowNoise,
     LowSpur = 0b11,
 }
-gen_bitfield_enum!(R2, NoiseMode, 2, 29);
+gen_bitfield_enum!(R2, NoiseMode, 2, 29, [LowNoise, LowSpur]);
 
 
 /// The on-chip multiplexer is controlled by Bits[DB28:DB26]
 /// (see Figure 26). Note that N counter output must be disabled
 /// for VCO band selection to operate correctly.
+///
+/// Encoding: codes 000 to 110 in variant order, 111 is reserved.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Muxout {
     ThreeStateOut,
     Dvdd,
@@ -303,7 +673,31 @@ pub enum Muxout {
     Alock,
     Dlock,
 }
-gen_bitfield_enum!(R2, Muxout, 3, 26);
+gen_bitfield_enum!(R2, Muxout, 3, 26, [ThreeStateOut, Dvdd, Dgnd, RCntOut, NDivOut, Alock, Dlock]);
+
+/// Lock detect signal on the MUXOUT pin.
+/// Both are high when locked, but the analog one is an N-channel open-drain
+/// output that needs an external pullup (10 kΩ nominal) and pulses low
+/// on phase errors, so a single high sample isn't conclusive.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LockDetectKind {
+    /// `Muxout::Dlock`, logic level output
+    Digital,
+    /// `Muxout::Alock`, open-drain output with narrow low pulses when unlocked
+    Analog,
+}
+
+impl Muxout {
+    /// Lock detect signal routed to MUXOUT, `None` for other modes.
+    pub fn lock_detect_kind(self) -> Option<LockDetectKind> {
+        match self {
+            Muxout::Dlock => Some(LockDetectKind::Digital),
+            Muxout::Alock => Some(LockDetectKind::Analog),
+            _ => None,
+        }
+    }
+}
 
 
 /// Setting the DB25 bit to 0 disables the doubler and feeds the REF IN
@@ -322,11 +716,13 @@ gen_bitfield_enum!(R2, Muxout, 3, 26);
 /// The maximum allowable REF IN frequency when the doubler is
 /// enabled is 30 MHz.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RefDoubler {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, RefDoubler, 1, 25);
+gen_bitfield_enum!(R2, RefDoubler, 1, 25, [Disabled, Enabled]);
 
 
 /// Setting the DB24 bit to 1 inserts a divide-by-2 toggle flip-flop
@@ -334,11 +730,13 @@ gen_bitfield_enum!(R2, RefDoubler, 1, 25);
 /// REF IN input rate. This function allows a 50% duty cycle signal to
 /// appear at the PFD input, which is necessary for cycle slip reduction.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Rdiv2 {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, Rdiv2, 1, 24);
+gen_bitfield_enum!(R2, Rdiv2, 1, 24, [Disabled, Enabled]);
 
 gen_bitfield_struct!(
     /// The 10-bit R counter (Bits[DB23:DB14]) allows the input reference
@@ -351,11 +749,13 @@ gen_bitfield_struct!(
 /// Bits[DB22:DB20] in Register 4. For information about how
 /// double buffering works, see the Program Modes section.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DoubleBuffer {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, DoubleBuffer, 1, 13);
+gen_bitfield_enum!(R2, DoubleBuffer, 1, 13, [Disabled, Enabled]);
 
 
 gen_bitfield_struct!(
@@ -366,6 +766,26 @@ gen_bitfield_struct!(
     , R2, ChargePumpCurrent, u8, 4, 9
 );
 
+/// Charge pump current for a 4 bit setting code, µA (RSET = 5.1 kΩ).
+/// Code 0b0000 is 312 µA, code 0b1111 is 5 mA, in 312.5 µA steps.
+pub fn charge_pump_current_ua(code: u8) -> u32 {
+    ((code & 0b1111) as u32 + 1) * CP_CURRENT_MAX_UA / 16
+}
+
+impl ChargePumpCurrent {
+    /// Charge pump current for this setting, µA (RSET = 5.1 kΩ).
+    pub fn ua(self) -> u32 {
+        charge_pump_current_ua(self.0)
+    }
+
+    /// Setting closest to the requested charge pump current, µA (RSET = 5.1 kΩ).
+    /// Requests outside of the 312 µA to 5 mA range snap to the nearest end.
+    pub fn closest_to_ua(ua: u32) -> ChargePumpCurrent {
+        let steps = (ua as u64 * 16 + CP_CURRENT_MAX_UA as u64 / 2) / CP_CURRENT_MAX_UA as u64;
+        ChargePumpCurrent(steps.clamp(1, 16) as u8 - 1)
+    }
+}
+
 
 /// The DB8 bit configures the lock detect function (LDF). The LDF
 /// controls the number of PFD cycles monitored by the lock detect
@@ -375,11 +795,13 @@ gen_bitfield_struct!(
 /// mended that the DB8 bit be set to 0 for fractional-N mode and
 /// to 1 for integer-N mode.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ldf {
     FracN,
     IntN,
 }
-gen_bitfield_enum!(R2, Ldf, 1, 8);
+gen_bitfield_enum!(R2, Ldf, 1, 8, [FracN, IntN]);
 
 
 /// The lock detect precision bit (Bit DB7) sets the comparison
@@ -394,11 +816,13 @@ gen_bitfield_enum!(R2, Ldf, 1, 8);
 /// Bits[DB8:DB7] is 00; for integer-N applications, the recom-
 /// mended setting for Bits[DB8:DB7] is 11.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ldp {
     Ldp10ns,
     Ldp6ns,
 }
-gen_bitfield_enum!(R2, Ldp, 1, 7);
+gen_bitfield_enum!(R2, Ldp, 1, 7, [Ldp10ns, Ldp6ns]);
 
 
 /// The DB6 bit sets the phase detector polarity. When a passive
@@ -406,11 +830,13 @@ gen_bitfield_enum!(R2, Ldp, 1, 7);
 /// should be set to 1. If an active filter with an inverting charac-
 /// teristic is used, this bit should be set to 0.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PhaseDetectorPolarity {
     Negative,
     Positive,
 }
-gen_bitfield_enum!(R2, PhaseDetectorPolarity, 1, 6);
+gen_bitfield_enum!(R2, PhaseDetectorPolarity, 1, 6, [Negative, Positive]);
 
 
 /// The DB5 bit provides the programmable power-down mode.
@@ -427,21 +853,25 @@ gen_bitfield_enum!(R2, PhaseDetectorPolarity, 1, 6);
 /// * RF OUT buffers are disabled.
 /// * Input registers remain active and capable of loading and latching data.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PowerDown {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, PowerDown, 1, 5);
+gen_bitfield_enum!(R2, PowerDown, 1, 5, [Disabled, Enabled]);
 
 
 /// Setting the DB4 bit to 1 puts the charge pump into three-state
 /// mode. This bit should be set to 0 for normal operation.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChargePumpThreeState {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, ChargePumpThreeState, 1, 4);
+gen_bitfield_enum!(R2, ChargePumpThreeState, 1, 4, [Disabled, Enabled]);
 
 
 /// The DB3 bit is the reset bit for the R counter and the N counter
@@ -449,11 +879,13 @@ gen_bitfield_enum!(R2, ChargePumpThreeState, 1, 4);
 /// N counter and R counter are held in reset. For normal opera-
 /// tion, this bit should be set to 0.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CounterReset {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R2, CounterReset, 1, 3);
+gen_bitfield_enum!(R2, CounterReset, 1, 3, [Disabled, Enabled]);
 
 
 
@@ -465,11 +897,13 @@ gen_bitfield_enum!(R2, CounterReset, 1, 3);
 /// band select logic modes (DB23 set to 1), the value of the band
 /// select clock divider must be less than or equal to 254.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BandSelectClockMode {
     Low,
     High,
 }
-gen_bitfield_enum!(R3, BandSelectClockMode, 1, 23);
+gen_bitfield_enum!(R3, BandSelectClockMode, 1, 23, [Low, High]);
 
 
 /// Bit DB22 sets the PFD antibacklash pulse width. When Bit DB22
@@ -479,22 +913,26 @@ gen_bitfield_enum!(R3, BandSelectClockMode, 1, 23);
 /// noise and spur improvements in integer-N operation. For
 /// fractional-N operation, the 3 ns setting is not recommended.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AntiBacklashPulseWidth {
     AB6ns, // FRAC-N
     AB3ns, // INT-N
 }
-gen_bitfield_enum!(R3, AntiBacklashPulseWidth, 1, 22);
+gen_bitfield_enum!(R3, AntiBacklashPulseWidth, 1, 22, [AB6ns, AB3ns]);
 
 
 /// Setting the DB21 bit to 1 enables charge pump charge cancel-
 /// ation. This has the effect of reducing PFD spurs in integer-N
 /// mode. In fractional-N mode, this bit should be set to 0.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChargeCancellation {
     Disabled, // FRAC-N
     Enabled, // INT-N
 }
-gen_bitfield_enum!(R3, ChargeCancellation, 1, 21);
+gen_bitfield_enum!(R3, ChargeCancellation, 1, 21, [Disabled, Enabled]);
 
 
 /// Setting the DB18 bit to 1 enables cycle slip reduction. CSR is
@@ -504,11 +942,13 @@ gen_bitfield_enum!(R3, ChargeCancellation, 1, 21);
 /// must also be set to a minimum. For more information, see the
 /// Cycle Slip Reduction for Faster Lock Times section.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CycleSlipReduction {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R3, CycleSlipReduction, 1, 18);
+gen_bitfield_enum!(R3, CycleSlipReduction, 1, 18, [Disabled, Enabled]);
 
 
 /// Bits[DB16:DB15] must be set to 10 to activate phase resync
@@ -516,13 +956,17 @@ gen_bitfield_enum!(R3, CycleSlipReduction, 1, 18);
 /// to activate fast lock (see the Fast Lock Timer and Register
 /// Sequences section). Setting Bits[DB16:DB15] to 00 disables
 /// the clock divider (see Figure 27).
+///
+/// Encoding: 00 off, 01 fast lock, 10 resync, 11 is reserved.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClockDividerMode {
     Off,
     FastLock,
     Resync,
 }
-gen_bitfield_enum!(R3, ClockDividerMode, 2, 15);
+gen_bitfield_enum!(R3, ClockDividerMode, 2, 15, [Off, FastLock, Resync]);
 
 
 gen_bitfield_struct!(
@@ -547,11 +991,13 @@ gen_bitfield_struct!(
 /// PLLs are in phase. This is useful in some applications where the
 /// positive interference of signals is required to increase the power.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FeedbackSelect {
     Divided,
     Fundamental,
 }
-gen_bitfield_enum!(R4, FeedbackSelect, 1, 23);
+gen_bitfield_enum!(R4, FeedbackSelect, 1, 23, [Divided, Fundamental]);
 
 
 gen_bitfield_struct!(
@@ -562,6 +1008,24 @@ gen_bitfield_struct!(
 );
 
 
+/// RF Divider Select, typed version of `RfDividerSelect`.
+/// Bits[DB22:DB20] hold the exponent of the divide-by-2^n RF output
+/// divider, code 0b111 is not used.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RfDivider {
+    Div1,
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+}
+gen_bitfield_enum!(R4, RfDivider, 3, 20, [Div1, Div2, Div4, Div8, Div16, Div32, Div64]);
+
+
 gen_bitfield_struct!(
     /// Band Select Clock Divider Value
     /// Bits[DB19:DB12] set a divider for the band select logic clock input.
@@ -572,48 +1036,68 @@ gen_bitfield_struct!(
     , R4, BandSelectClockDiv, u8, 8, 12
 );
 
+impl BandSelectClockDiv {
+    /// Band select clock divider, 1 to 255, at most 254 in the high (fast) mode.
+    /// Use the tuple constructor for raw values.
+    pub fn new(div: u8, mode: BandSelectClockMode) -> Result<BandSelectClockDiv, Error> {
+        match (div, mode) {
+            (0, _) => Err(Error::InvalidBandSelectClockDiv),
+            (div, BandSelectClockMode::High) if div > BAND_SELECT_CLOCK_DIV_HIGH_MAX => Err(Error::InvalidBandSelectClockDiv),
+            (div, _) => Ok(BandSelectClockDiv(div)),
+        }
+    }
+}
+
 
 /// Setting the DB11 bit to 0 powers the VCO up; setting this bit to 1
 /// powers the VCO down.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VcoPowerDown {
     PoweredUp,
     PoweredDown,
 }
-gen_bitfield_enum!(R4, VcoPowerDown, 1, 11);
+gen_bitfield_enum!(R4, VcoPowerDown, 1, 11, [PoweredUp, PoweredDown]);
 
 
 /// When the DB10 bit is set to 1, the supply current to the RF output
 /// stage is shut down until the part achieves lock, as measured by
 /// the digital lock detect circuitry.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MuteTillLockDetect {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R4, MuteTillLockDetect, 1, 10);
+gen_bitfield_enum!(R4, MuteTillLockDetect, 1, 10, [Disabled, Enabled]);
 
 
 /// The DB9 bit sets the auxiliary RF output. If DB9 is set to 0, the
 /// auxiliary RF output is the output of the RF dividers; if DB9 is set
 /// to 1, the auxiliary RF output is the fundamental VCO frequency.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AuxOutputSelect {
     Divided,
     Fundamental,
 }
-gen_bitfield_enum!(R4, AuxOutputSelect, 1, 9);
+gen_bitfield_enum!(R4, AuxOutputSelect, 1, 9, [Divided, Fundamental]);
 
 
 /// The DB8 bit enables or disables the auxiliary RF output. If DB8
 /// is set to 0, the auxiliary RF output is disabled; if DB8 is set to 1,
 /// the auxiliary RF output is enabled.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AuxOutputEnable {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R4, AuxOutputEnable, 1, 8);
+gen_bitfield_enum!(R4, AuxOutputEnable, 1, 8, [Disabled, Enabled]);
 
 gen_bitfield_struct!(
     /// AUX Output Power
@@ -627,11 +1111,13 @@ gen_bitfield_struct!(
 /// is set to 0, the primary RF output is disabled; if DB5 is set to 1,
 /// the primary RF output is enabled.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RfOutputEnable {
     Disabled,
     Enabled,
 }
-gen_bitfield_enum!(R4, RfOutputEnable, 1, 5);
+gen_bitfield_enum!(R4, RfOutputEnable, 1, 5, [Disabled, Enabled]);
 
 
 gen_bitfield_struct!(
@@ -642,13 +1128,268 @@ gen_bitfield_struct!(
 );
 
 
+/// Output Power, typed version of `OutputPower`.
+/// Bits[DB4:DB3] select one of four primary RF output power levels.
+/// Use `AuxRfPower` for the auxiliary output Bits[DB7:DB6].
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RfPower {
+    Minus4dBm,
+    Minus1dBm,
+    Plus2dBm,
+    Plus5dBm,
+}
+gen_bitfield_enum!(R4, RfPower, 2, 3, [Minus4dBm, Minus1dBm, Plus2dBm, Plus5dBm]);
+
+impl RfPower {
+    /// Power level from its dBm value, `None` if there's no such level.
+    pub fn from_dbm(dbm: i8) -> Option<RfPower> {
+        match dbm {
+            -4 => Some(RfPower::Minus4dBm),
+            -1 => Some(RfPower::Minus1dBm),
+            2 => Some(RfPower::Plus2dBm),
+            5 => Some(RfPower::Plus5dBm),
+            _ => None,
+        }
+    }
+
+    /// Power level in dBm
+    pub fn dbm(self) -> i8 {
+        match self {
+            RfPower::Minus4dBm => -4,
+            RfPower::Minus1dBm => -1,
+            RfPower::Plus2dBm => 2,
+            RfPower::Plus5dBm => 5,
+        }
+    }
+}
+
+
+/// AUX Output Power, typed version of `AuxOutputPower`.
+/// Bits[DB7:DB6] select one of four auxiliary RF output power levels.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuxRfPower(pub RfPower);
+
+gen_bitfield_impl!(R4, AuxRfPower, 2, 6, AuxRfPower::from);
+
+impl From<u32> for AuxRfPower { #[inline] fn from(x: u32) -> Self { AuxRfPower(RfPower::from_bits_or_default(x)) } }
+impl From<AuxRfPower> for u32 { #[inline] fn from(x: AuxRfPower) -> u32 { x.0.into() } }
+
+impl AuxRfPower {
+    /// Field value, const version of `u32::from`
+    #[inline] pub const fn bits(self) -> u32 { self.0 as u32 }
+}
+
+
 /// Bits[DB23:DB22] set the operation of the lock detect (LD) pin
 /// (see Figure 29).
+///
+/// Encoding: 00 low, 01 digital lock detect, 10 low, 11 high.
+/// Both low codes behave the same, so only 00 is used, 10 decodes as `Low`
+/// through `from_bits_or_default` and as `None` through `from_code`.
 #[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LockDetectPin {
-    Low,
-    DigitalLockDetect,
-    Low1,
-    High,
+    /// LD pin driven low (00)
+    Low = 0b00,
+    /// Digital lock detect, high when locked (01)
+    DigitalLockDetect = 0b01,
+    /// LD pin driven high (11)
+    High = 0b11,
+}
+gen_bitfield_enum!(R5, LockDetectPin, 2, 22, [Low, DigitalLockDetect, High]);
+
+impl LockDetectPin {
+    /// Recommended setting, LD pin shows digital lock detect
+    pub const fn digital_lock_detect() -> LockDetectPin {
+        LockDetectPin::DigitalLockDetect
+    }
+}
+
+
+
+/// Serialized as a format version and six words in device format, R0 first
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{ de, Deserialize, Deserializer, Serialize, Serializer, };
+
+    use super::RegisterSet;
+
+    const FORMAT_VERSION: u8 = 1;
+
+    #[derive(Serialize, Deserialize)]
+    struct RegisterSetWords {
+        version: u8,
+        words: [u32; 6],
+    }
+
+    impl Serialize for RegisterSet {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            RegisterSetWords { version: FORMAT_VERSION, words: self.to_words() }.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for RegisterSet {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let rsw = RegisterSetWords::deserialize(deserializer)?;
+            if rsw.version != FORMAT_VERSION {
+                return Err(de::Error::custom("unsupported RegisterSet format version"));
+            }
+            RegisterSet::from_words(&rsw.words)
+                .map_err(|_| de::Error::custom("register word control bits don't match register number"))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(rs: &RegisterSet) -> [u32; 6] {
+        [rs.r0.w, rs.r1.w, rs.r2.w, rs.r3.w, rs.r4.w, rs.r5.w]
+    }
+
+    /// Sets the field over all-zero and all-one register sets,
+    /// checks that it reads back and that no other bit changes.
+    fn round_trip<F, R>(f: F)
+    where F: Copy + BitField<R> + Into<u32>,
+          RegisterSet: RIdx<R>
+    {
+        assert!(F::offset() >= 3 && F::offset() + F::num_bits() <= 32);
+        let field_bits = F::mask() << F::offset();
+        let value : u32 = f.into();
+
+        for fill in [0, !CONTROL_BITS_MASK] {
+            let rs = RegisterSet::with_words([fill, fill | 1, fill | 2, fill | 3, fill | 4, fill | 5]);
+            let updated = rs.set(f);
+            let got : F = updated.get();
+            assert_eq!(got.into(), value);
+
+            let (before, after) = (words(&rs), words(&updated));
+            let diff = before.iter().zip(after.iter()).map(|(a, b)| a ^ b);
+            assert!(diff.clone().filter(|d| *d != 0).count() <= 1);
+            assert!(diff.into_iter().all(|d| d & !field_bits == 0));
+        }
+    }
+
+    macro_rules! round_trip_structs {
+        ($($n:ident),*) => { $(
+            for v in [0, 1, 0xAAAA_AAAA & $n::MASK, 0x5555_5555 & $n::MASK, $n::MASK] {
+                round_trip($n::from_bits(v));
+            }
+        )* }
+    }
+
+    macro_rules! round_trip_enums {
+        ($($n:ident :: [$($v:ident),*]),*) => { $( $(
+            round_trip($n::$v);
+            assert!(matches!($n::try_from($n::$v.bits()), Ok($n::$v)));
+            assert!(matches!($n::from_code($n::$v.code()), Some($n::$v)));
+        )* )* }
+    }
+
+    #[test]
+    fn bitfields_round_trip() {
+        round_trip_structs!(Int, Frac, Phase, Mod, R, ChargePumpCurrent, ClockDividerValue,
+                            RfDividerSelect, BandSelectClockDiv, AuxOutputPower, OutputPower);
+        round_trip_enums!(
+            Ph1PhaseAdj::[Off, On],
+            Pr1Prescaler::[Pr45, Pr89],
+            NoiseMode::[LowNoise, LowSpur],
+            Muxout::[ThreeStateOut, Dvdd, Dgnd, RCntOut, NDivOut, Alock, Dlock],
+            RefDoubler::[Disabled, Enabled],
+            Rdiv2::[Disabled, Enabled],
+            DoubleBuffer::[Disabled, Enabled],
+            Ldf::[FracN, IntN],
+            Ldp::[Ldp10ns, Ldp6ns],
+            PhaseDetectorPolarity::[Negative, Positive],
+            PowerDown::[Disabled, Enabled],
+            ChargePumpThreeState::[Disabled, Enabled],
+            CounterReset::[Disabled, Enabled],
+            BandSelectClockMode::[Low, High],
+            AntiBacklashPulseWidth::[AB6ns, AB3ns],
+            ChargeCancellation::[Disabled, Enabled],
+            CycleSlipReduction::[Disabled, Enabled],
+            ClockDividerMode::[Off, FastLock, Resync],
+            FeedbackSelect::[Divided, Fundamental],
+            RfDivider::[Div1, Div2, Div4, Div8, Div16, Div32, Div64],
+            VcoPowerDown::[PoweredUp, PoweredDown],
+            MuteTillLockDetect::[Disabled, Enabled],
+            AuxOutputSelect::[Divided, Fundamental],
+            AuxOutputEnable::[Disabled, Enabled],
+            RfOutputEnable::[Disabled, Enabled],
+            RfPower::[Minus4dBm, Minus1dBm, Plus2dBm, Plus5dBm],
+            LockDetectPin::[Low, DigitalLockDetect, High]
+        );
+        for p in [RfPower::Minus4dBm, RfPower::Minus1dBm, RfPower::Plus2dBm, RfPower::Plus5dBm] {
+            round_trip(AuxRfPower(p));
+        }
+    }
+
+    #[test]
+    fn adi_hex_round_trip() {
+        let rs = RegisterSet::recommended_defaults().set(Int(161)).set(Frac(1120)).set(Mod(4000));
+        let mut buf = [0u8; ADI_HEX_LEN];
+        assert_eq!(rs.to_adi_hex(&mut buf), Ok(ADI_HEX_LEN));
+
+        let s = core::str::from_utf8(&buf).unwrap();
+        assert_eq!(s.lines().next(), Some("0x0050A300"));
+        assert_eq!(RegisterSet::from_adi_hex(s), Ok(rs));
+
+        assert_eq!(rs.to_adi_hex(&mut buf[1 ..]), Err(Error::BufferTooSmall));
+        assert_eq!(RegisterSet::from_adi_hex("0x0\n0x1\n"), Err(Error::InvalidHex));
+    }
+
+    #[test]
+    fn band_select_clock_div_limits() {
+        assert_eq!(BandSelectClockDiv::new(255, BandSelectClockMode::Low).map(|d| d.0), Ok(255));
+        assert_eq!(BandSelectClockDiv::new(254, BandSelectClockMode::High).map(|d| d.0), Ok(254));
+        assert!(matches!(BandSelectClockDiv::new(255, BandSelectClockMode::High), Err(Error::InvalidBandSelectClockDiv)));
+        assert!(matches!(BandSelectClockDiv::new(0, BandSelectClockMode::Low), Err(Error::InvalidBandSelectClockDiv)));
+    }
+
+    #[test]
+    fn unknown_codes_rejected() {
+        assert_eq!(Muxout::try_from(0b111).err(), Some(Error::InvalidFieldValue));
+        assert_eq!(NoiseMode::try_from(0b01).err(), Some(Error::InvalidFieldValue));
+        assert_eq!(LockDetectPin::try_from(0b10).err(), Some(Error::InvalidFieldValue));
+        assert_eq!(RfDivider::try_from(0b111).err(), Some(Error::InvalidFieldValue));
+        assert_eq!(Rdiv2::try_from(2).err(), Some(Error::InvalidFieldValue));
+    }
+
+    #[test]
+    fn unknown_codes_decode_to_default() {
+        // 0b111 is reserved
+        assert!(matches!(Muxout::from_bits_or_default(0b111), Muxout::ThreeStateOut));
+        assert!(matches!(LockDetectPin::from_bits_or_default(0b10), LockDetectPin::Low));
+        assert!(matches!(RfDivider::from_bits_or_default(0b111), RfDivider::Div1));
+        // struct fields drop bits above their width, like `get`
+        assert_eq!(Mod::from(0x1001).0, 1);
+        assert_eq!(BandSelectClockDiv::from(0x1FF).0, 0xFF);
+    }
+
+    #[test]
+    fn reserved_codes_have_no_variant() {
+        assert!(Muxout::from_code(0b111).is_none());
+        assert!(NoiseMode::from_code(0b01).is_none());
+        assert!(NoiseMode::from_code(0b10).is_none());
+        assert!(ClockDividerMode::from_code(0b11).is_none());
+        assert!(LockDetectPin::from_code(0b10).is_none());
+        assert!(matches!(NoiseMode::from_code(0b11), Some(NoiseMode::LowSpur)));
+        assert_eq!(LockDetectPin::High.code(), 0b11);
+        // wider than the field
+        assert!(ClockDividerMode::from_code(0b101).is_none());
+    }
+
+    #[test]
+    fn frac_below_mod() {
+        assert_eq!(Frac::checked(3999, 4000).map(|f| f.0), Ok(3999));
+        assert!(matches!(Frac::checked(4000, 4000), Err(Error::InvalidFrac)));
+        assert!(matches!(Frac::checked(4096, 5000), Err(Error::InvalidFrac)));
+    }
 }
-gen_bitfield_enum!(R5, LockDetectPin, 2, 22);
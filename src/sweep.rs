@@ -0,0 +1,224 @@
+//! Output frequency sweeps
+
+use crate::{ config::*, errors::*, register::*, };
+
+
+/// Single sweep step
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SweepPoint {
+    /// Requested output frequency, Hz
+    pub f_set_hz: u64,
+    /// Actual output frequency, Hz
+    pub f_out_hz: u64,
+    /// Registers for this step
+    pub rs: RegisterSet,
+}
+
+
+/// Steps FRAC-N output frequency from start to stop (inclusive) frequency.
+///
+/// Each step only updates INT/FRAC, prescaler and RF divider of the previous
+/// step's registers, all other settings are kept from the initial register set.
+/// The prescaler follows `choose_prescaler_hysteretic`, so a sweep near
+/// 3.6 GHz doesn't switch it back and forth.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sweep {
+    ref_in_hz: u32,
+    fracn: FracN,
+    rs: RegisterSet,
+    f_next_hz: Option<u64>,
+    f_stop_hz: u64,
+    step_hz: u64,
+    prescaler: Pr1Prescaler,
+}
+
+impl Sweep {
+    /// Sweeps from `f_start_hz` to `f_stop_hz` in `step_hz` increments,
+    /// `rs` should be initialized for FRAC-N mode, see `FracN::init`.
+    pub fn new(
+        ref_in_hz: u32,
+        f_start_hz: u64,
+        f_stop_hz: u64,
+        step_hz: u64,
+        rs: RegisterSet,
+    ) -> Result<Self, Error> {
+        if step_hz == 0 {
+            return Err(Error::InvalidSweepStep);
+        }
+        let fracn = FracN(Fpfd::new(ref_in_hz, &rs)?);
+        Ok(Sweep { ref_in_hz, fracn, rs, f_next_hz: Some(f_start_hz), f_stop_hz, step_hz, prescaler: rs.get(), })
+    }
+}
+
+impl Iterator for Sweep {
+    type Item = Result<SweepPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let f_set_hz = self.f_next_hz.filter(|f| *f <= self.f_stop_hz)?;
+        self.f_next_hz = f_set_hz.checked_add(self.step_hz);
+
+        let point = self.fracn.set_f_out(f_set_hz, self.rs).and_then(|rs| {
+            let rs = keep_prescaler_hysteretic(self.fracn.0, self.prescaler, rs);
+            self.prescaler = rs.get();
+            self.rs = rs;
+            let f_out_hz = FracN::f_out_hz(self.ref_in_hz, &rs)?;
+            Ok(SweepPoint { f_set_hz, f_out_hz, rs, })
+        });
+        Some(point)
+    }
+}
+
+
+/// Steps output phase over a full turn in equal steps at a fixed frequency,
+/// e.g. for BPSK/QPSK style phase keying.
+///
+/// Yields register sets that only differ in R1 phase word, with phase adjust on
+/// (`Ph1PhaseAdj::On`), so R0 updates don't trigger VCO band selection.
+#[derive(Debug,Copy,Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PhaseSweep {
+    rs: RegisterSet,
+    modulus: u16,
+    steps: u16,
+    step_next: u16,
+}
+
+impl PhaseSweep {
+    /// `steps` phase steps of 360°/`steps` each, starting at 0°.
+    /// Phase resolution is 360°/MOD, so `steps` can't be more than the current MOD.
+    pub fn new(
+        steps: u16,
+        rs: RegisterSet,
+    ) -> Result<Self, Error> {
+        let modulus : Mod = rs.get();
+        if steps == 0 || steps > modulus.0 {
+            return Err(Error::InvalidSweepStep);
+        }
+        Ok(PhaseSweep { rs: rs.set(Ph1PhaseAdj::On), modulus: modulus.0, steps, step_next: 0, })
+    }
+}
+
+impl Iterator for PhaseSweep {
+    type Item = RegisterSet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.step_next >= self.steps {
+            return None;
+        }
+        // closest phase word to step × MOD / steps
+        let (step, m, n) = (self.step_next as u32, self.modulus as u32, self.steps as u32);
+        let phase = (step * m + n / 2) / n;
+        self.step_next += 1;
+        Some(self.rs.set(Phase(phase as u16)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::*;
+
+    fn fracn_registers() -> RegisterSet {
+        FracN::init(RegisterSet::recommended_defaults().set(Mod(4000)))
+    }
+
+    #[test]
+    fn sweep_includes_stop() {
+        let sweep = Sweep::new(25_000_000, 1_000_000_000, 1_000_200_000, 100_000, fracn_registers()).unwrap();
+        let f_set = [1_000_000_000, 1_000_100_000, 1_000_200_000];
+        assert!(sweep.map(|p| p.unwrap().f_set_hz).eq(f_set.iter().copied()));
+    }
+
+    #[test]
+    fn sweep_stops_before_passing_stop() {
+        let sweep = Sweep::new(25_000_000, 1_000_000_000, 1_000_250_000, 100_000, fracn_registers()).unwrap();
+        assert_eq!(sweep.map(|p| p.unwrap().f_set_hz).last(), Some(1_000_200_000));
+
+        // start past stop, nothing to do
+        assert_eq!(Sweep::new(25_000_000, 2, 1, 1, fracn_registers()).unwrap().count(), 0);
+        // no wrap around at the end of the range
+        assert_eq!(Sweep::new(25_000_000, u64::MAX, u64::MAX, 1, fracn_registers()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn sweep_rejects_zero_step() {
+        assert_eq!(Sweep::new(25_000_000, 1_000_000_000, 1_000_200_000, 0, fracn_registers()).err(), Some(Error::InvalidSweepStep));
+    }
+
+    #[test]
+    fn sweep_reports_actual_frequency() {
+        // 1 kHz steps are below the 25 MHz / 4000 / 4 resolution at 1 GHz
+        let sweep = Sweep::new(25_000_000, 1_000_000_000, 1_000_003_000, 1_000, fracn_registers()).unwrap();
+        let mut inexact = 0;
+        for point in sweep {
+            let point = point.unwrap();
+            assert_eq!(FracN::f_out_hz(25_000_000, &point.rs), Ok(point.f_out_hz));
+            assert!(point.f_out_hz <= point.f_set_hz && point.f_set_hz - point.f_out_hz < 1_563);
+            if point.f_out_hz != point.f_set_hz {
+                inexact += 1;
+            }
+        }
+        assert!(inexact > 0);
+    }
+
+    #[test]
+    fn sweep_keeps_prescaler_near_threshold() {
+        let rs = fracn_registers();
+        let prescalers = |start, rs| -> [bool; 5] {
+            let mut p = [false; 5];
+            let sweep = Sweep::new(25_000_000, start, start + 40_000_000, 10_000_000, rs).unwrap();
+            for (i, point) in sweep.enumerate() {
+                p[i] = matches!(point.unwrap().rs.get(), Pr1Prescaler::Pr89);
+            }
+            p
+        };
+        // 3.59 to 3.63 GHz, 8/9 from the previous step stays within the guard band
+        assert_eq!(prescalers(3_590_000_000, rs.set(Pr1Prescaler::Pr89)), [true; 5]);
+        // switches only once, above 3.6 GHz, starting from 4/5
+        assert_eq!(prescalers(3_590_000_000, rs.set(Pr1Prescaler::Pr45)), [false, false, true, true, true]);
+        // well below the guard band 4/5 is picked again
+        assert_eq!(prescalers(3_000_000_000, rs.set(Pr1Prescaler::Pr89)), [false; 5]);
+    }
+
+    #[test]
+    fn phase_sweep_needs_steps_within_mod() {
+        let rs = fracn_registers().set(Mod(4));
+        assert_eq!(PhaseSweep::new(5, rs).err(), Some(Error::InvalidSweepStep));
+        assert_eq!(PhaseSweep::new(0, rs).err(), Some(Error::InvalidSweepStep));
+        assert_eq!(PhaseSweep::new(4, rs).map(|s| s.count()), Ok(4));
+    }
+
+    #[test]
+    fn phase_sweep_steps_r1_phase_only() {
+        let rs = fracn_registers();
+        let mut prev : Option<RegisterSet> = None;
+        let mut degrees = [0u16; 4];
+        for (i, step) in PhaseSweep::new(4, rs).unwrap().enumerate() {
+            assert!(matches!(step.get(), Ph1PhaseAdj::On));
+            let phase : Phase = step.get();
+            degrees[i] = phase.to_degrees(4000);
+            if let Some(prev) = prev {
+                assert_eq!(step.diff(&prev), [false, true, false, false, false, false]);
+            }
+            let (w, w0) = (step.to_words(), rs.to_words());
+            assert_eq!((w[0], &w[2 ..]), (w0[0], &w0[2 ..]));
+            prev = Some(step);
+        }
+        assert_eq!(degrees, [0, 90, 180, 270]);
+
+        // 3 steps of 4000 / 3 round to the closest phase word
+        let words : [u16; 3] = [0, 1333, 2667];
+        assert!(PhaseSweep::new(3, rs).unwrap().map(|s| { let p : Phase = s.get(); p.0 }).eq(words.iter().copied()));
+    }
+
+    #[test]
+    fn sweep_passes_errors_through() {
+        let mut sweep = Sweep::new(25_000_000, OUT_FREQ_MIN - 1_000_000, OUT_FREQ_MIN, 1_000_000, fracn_registers()).unwrap();
+        assert_eq!(sweep.next().map(|p| p.err()), Some(Some(Error::InvalidOutputFrequency)));
+        assert_eq!(sweep.next().map(|p| p.map(|p| p.f_out_hz)), Some(Ok(OUT_FREQ_MIN)));
+        assert!(sweep.next().is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! Frequency units
+
+use core::convert::TryFrom;
+
+use crate::errors::*;
+
+
+/// Frequency, Hz, so that MHz/kHz values can't lose a zero.
+/// Output frequencies take `impl Into<Hertz>` (bare `u64` Hz still works),
+/// REF IN has `Hertz` variants, e.g. `RefIn::new_hertz`, `Fpfd::new_hertz`.
+/// Converts into the bare Hz values the rest of the crate takes, e.g.
+///
+/// ```
+/// use adf4351::units::Hertz;
+/// use core::convert::TryInto;
+///
+/// let f_out : u64 = Hertz::mhz(1_000).into();
+/// let ref_in : u32 = Hertz::mhz(25).try_into().unwrap();
+/// assert_eq!((f_out, ref_in), (1_000_000_000, 25_000_000));
+/// ```
+#[derive(Debug,Copy,Clone,PartialEq,Eq,PartialOrd,Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hertz(pub u64);
+
+impl Hertz {
+    /// Hz
+    #[inline] pub const fn hz(f: u64) -> Self { Hertz(f) }
+
+    /// kHz
+    #[inline] pub const fn khz(f: u32) -> Self { Hertz(f as u64 * 1_000) }
+
+    /// MHz
+    #[inline] pub const fn mhz(f: u32) -> Self { Hertz(f as u64 * 1_000_000) }
+}
+
+impl From<u64> for Hertz { #[inline] fn from(f: u64) -> Self { Hertz(f) } }
+impl From<Hertz> for u64 { #[inline] fn from(f: Hertz) -> u64 { f.0 } }
+
+/// REF IN and PFD frequencies are `u32`, larger values don't fit
+impl TryFrom<Hertz> for u32 {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(f: Hertz) -> Result<u32, Error> {
+        u32::try_from(f.0).map_err(|_| Error::FrequencyOutOfRange)
+    }
+}